@@ -0,0 +1,126 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+use nalgebra_glm::{Vec2, Vec3};
+
+use model3d::color::Color;
+use model3d::fragment::Fragment;
+use model3d::light::Light;
+use model3d::render::shade_fragments;
+use model3d::shaders::{
+    earth_shader, jupiter_shader, mars_shader, mercury_shader, moon_shader, neptune_shader, saturn_shader,
+    select_shader, sun_shader, uranus_shader, venus_shader,
+};
+use model3d::uniforms::{create_perspective_matrix, create_viewport_matrix, Uniforms};
+
+type Shader = fn(&Fragment, &Uniforms) -> Color;
+
+fn sample_fragment() -> Fragment {
+    let position = Vec3::new(1.2, 2.1, 2.4);
+    Fragment::new(
+        Vec2::new(400.0, 300.0),
+        Color::new(100, 100, 100),
+        0.5,
+        position.normalize(),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec2::new(0.25, 0.5),
+        position,
+        position,
+    )
+}
+
+// One fragment per pixel of a 400x400 patch, spread over the front half of
+// a sphere, about what a planet filling the window produces
+fn sample_fragments() -> Vec<Fragment> {
+    let size = 400;
+    let mut fragments = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let u = x as f32 / size as f32 * 2.0 - 1.0;
+            let v = y as f32 / size as f32 * 2.0 - 1.0;
+            let z = (1.0 - u * u - v * v).max(0.0).sqrt();
+            let mut fragment = sample_fragment();
+            fragment.position = Vec2::new(x as f32, y as f32);
+            fragment.normal = Vec3::new(u, v, z).normalize();
+            fragment.world_position = fragment.normal * 3.5;
+            fragment.vertex_position = fragment.world_position;
+            fragments.push(fragment);
+        }
+    }
+    fragments
+}
+
+fn sample_uniforms() -> Uniforms {
+    Uniforms::new(
+        create_perspective_matrix(800.0, 600.0),
+        create_viewport_matrix(800.0, 600.0),
+    )
+}
+
+fn bench_shaders(c: &mut Criterion) {
+    let fragment = sample_fragment();
+    let uniforms = sample_uniforms();
+    let sun_light = Light::new_sun();
+
+    let shaders: [(&str, Shader); 9] = [
+        ("mercury", mercury_shader),
+        ("venus", venus_shader),
+        ("earth", earth_shader),
+        ("mars", mars_shader),
+        ("jupiter", jupiter_shader),
+        ("saturn", saturn_shader),
+        ("uranus", uranus_shader),
+        ("neptune", neptune_shader),
+        ("moon", moon_shader),
+    ];
+
+    let mut group = c.benchmark_group("shaders");
+    group.bench_function("sun", |b| {
+        b.iter(|| sun_shader(black_box(&fragment), black_box(&uniforms), &sun_light))
+    });
+    for (name, shader) in shaders {
+        group.bench_function(name, |b| {
+            b.iter(|| shader(black_box(&fragment), black_box(&uniforms)))
+        });
+    }
+
+    // Cost of building a noise generator inside a shader, once per fragment
+    group.bench_function("noise_per_fragment", |b| {
+        b.iter(|| {
+            let mut noise = FastNoiseLite::new();
+            noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+            let position = black_box(&fragment).vertex_position;
+            noise.get_noise_2d(position.x * 10.0, position.y * 10.0)
+        })
+    });
+    group.finish();
+}
+
+// Serial and parallel fragment stage; both must produce the same colors
+fn bench_fragment_shading(c: &mut Criterion) {
+    let fragments = sample_fragments();
+    let uniforms = sample_uniforms();
+    let earth = 2;
+
+    let serial = |fragments: &[Fragment]| -> Vec<(Color, f32, f32)> {
+        fragments
+            .iter()
+            .map(|fragment| select_shader(earth, fragment, &uniforms))
+            .collect()
+    };
+    let matches = serial(&fragments)
+        .iter()
+        .zip(shade_fragments(&fragments, &uniforms, earth))
+        .all(|(a, b)| a.0.to_hex() == b.0.to_hex() && a.1 == b.1 && a.2 == b.2);
+    assert!(matches, "parallel shading differs from the serial loop");
+
+    let mut group = c.benchmark_group("fragment_shading");
+    group.sample_size(20);
+    group.bench_function("serial", |b| b.iter(|| serial(black_box(&fragments))));
+    group.bench_function("parallel", |b| {
+        b.iter(|| shade_fragments(black_box(&fragments), &uniforms, earth))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_shaders, bench_fragment_shading);
+criterion_main!(benches);
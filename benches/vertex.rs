@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nalgebra_glm::Vec3;
+
+use model3d::obj::{Obj, MAX_SPHERE_SEGMENTS};
+use model3d::render::transform_vertices;
+use model3d::shaders::vertex_shader;
+use model3d::uniforms::{
+    create_model_matrix, create_perspective_matrix, create_view_matrix, create_viewport_matrix,
+    Uniforms,
+};
+use model3d::vertex::Vertex;
+
+fn sample_uniforms() -> Uniforms {
+    let mut uniforms = Uniforms::new(
+        create_perspective_matrix(800.0, 600.0),
+        create_viewport_matrix(800.0, 600.0),
+    );
+    uniforms.model_matrix = create_model_matrix(Vec3::zeros(), 1.0, Vec3::zeros());
+    uniforms.view_matrix = create_view_matrix(
+        Vec3::new(0.0, 0.0, 20.0),
+        Vec3::zeros(),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    uniforms
+}
+
+// Serial and parallel vertex stage on the most subdivided generated sphere
+fn bench_vertex_transform(c: &mut Criterion) {
+    let uniforms = sample_uniforms();
+    let sphere = Obj::uv_sphere(MAX_SPHERE_SEGMENTS, MAX_SPHERE_SEGMENTS, 3.5).get_vertex_array();
+
+    let mut group = c.benchmark_group("vertex_transform");
+    group.sample_size(20);
+    group.bench_function("serial", |b| {
+        b.iter(|| {
+            black_box(&sphere)
+                .iter()
+                .map(|vertex| vertex_shader(vertex, &uniforms))
+                .collect::<Vec<Vertex>>()
+        })
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| transform_vertices(black_box(&sphere), &uniforms))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_vertex_transform);
+criterion_main!(benches);
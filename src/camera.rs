@@ -1,6 +1,36 @@
-use nalgebra_glm::{Vec3, rotate_vec3};
+use nalgebra_glm::{Vec3, rotate_vec3, lerp};
 use std::f32::consts::PI;
 
+const BOOKMARK_SLOTS: usize = 9;
+// Seconds a restored bookmark takes to reach its viewpoint
+const BOOKMARK_TRANSITION_SECONDS: f32 = 0.5;
+// Just under 90 degrees so the eye never crosses the pole and flips the view
+const MAX_ORBIT_PITCH: f32 = PI / 2.0 - 0.02;
+// A press that travels less than this many pixels before release is a click
+const CLICK_SLOP: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraState {
+  pub eye: Vec3,
+  pub center: Vec3,
+  pub up: Vec3,
+}
+
+// What the orbit controls revolve around
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrbitTarget {
+  System,
+  Planet,
+}
+
+// How the movement keys drive the camera: revolving around the center, or
+// flying freely with the center always straight ahead
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+  Orbit,
+  Fly,
+}
+
 pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
@@ -41,7 +71,7 @@ impl Camera {
     let current_pitch = (-radius_vector.y).atan2(radius_xz);
 
     let new_yaw = (current_yaw + delta_yaw) % (2.0 * PI);
-    let new_pitch = (current_pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+    let new_pitch = (current_pitch + delta_pitch).clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
 
     let new_eye = self.center + Vec3::new(
       radius * new_yaw.cos() * new_pitch.cos(),
@@ -59,6 +89,25 @@ impl Camera {
     self.has_changed = true;
   }
 
+  pub fn distance(&self) -> f32 {
+    (self.eye - self.center).magnitude()
+  }
+
+  // Places the eye at exactly `distance` from the center along the current
+  // view direction. If eye and center coincide there is no direction, so
+  // the eye is pushed back along +Z.
+  pub fn set_distance(&mut self, distance: f32) {
+    let offset = self.eye - self.center;
+    let direction = if offset.magnitude() > f32::EPSILON {
+      offset.normalize()
+    } else {
+      Vec3::new(0.0, 0.0, 1.0)
+    };
+
+    self.eye = self.center + direction * distance.max(0.0);
+    self.has_changed = true;
+  }
+
   pub fn move_center(&mut self, direction: Vec3) {
     let radius_vector = self.center - self.eye;
     let radius = radius_vector.magnitude();
@@ -75,6 +124,76 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Free-fly: turns the view about the eye, positive yaw to the right and
+  // positive pitch upwards, stopping short of straight up or down. The center
+  // keeps its distance, so orbiting afterwards revolves around what is ahead.
+  pub fn look(&mut self, delta_yaw: f32, delta_pitch: f32) {
+    let view = self.center - self.eye;
+    let distance = view.magnitude();
+    if distance <= f32::EPSILON {
+      return;
+    }
+
+    let horizontal = (view.x * view.x + view.z * view.z).sqrt();
+    let yaw = view.z.atan2(view.x) + delta_yaw;
+    let pitch = (view.y.atan2(horizontal) + delta_pitch).clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
+
+    let direction = Vec3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+    self.center = self.eye + direction * distance;
+    self.has_changed = true;
+  }
+
+  // Free-fly: moves eye and center along the view direction
+  pub fn move_forward(&mut self, distance: f32) {
+    let forward = (self.center - self.eye).normalize();
+    self.translate(forward * distance);
+  }
+
+  // Free-fly: strafes sideways, perpendicular to the view direction
+  pub fn move_right(&mut self, distance: f32) {
+    let forward = (self.center - self.eye).normalize();
+    let right = forward.cross(&self.up).normalize();
+    self.translate(right * distance);
+  }
+
+  // Free-fly: rises or sinks along the up vector
+  pub fn move_up(&mut self, distance: f32) {
+    let up = self.up.normalize();
+    self.translate(up * distance);
+  }
+
+  fn translate(&mut self, offset: Vec3) {
+    self.eye += offset;
+    self.center += offset;
+    self.has_changed = true;
+  }
+
+  // Moves eye and center together so the center lands on `target`,
+  // keeping the current viewing angle and distance
+  pub fn follow(&mut self, target: Vec3) {
+    let offset = target - self.center;
+    if offset.magnitude() > 0.0 {
+      self.eye += offset;
+      self.center = target;
+      self.has_changed = true;
+    }
+  }
+
+  pub fn state(&self) -> CameraState {
+    CameraState {
+      eye: self.eye,
+      center: self.center,
+      up: self.up,
+    }
+  }
+
+  pub fn set_state(&mut self, state: &CameraState) {
+    self.eye = state.eye;
+    self.center = state.center;
+    self.up = state.up;
+    self.has_changed = true;
+  }
+
   pub fn check_if_changed(&mut self) -> bool {
     if self.has_changed {
       self.has_changed = false;
@@ -83,4 +202,397 @@ impl Camera {
       false
     }
   }
-}
\ No newline at end of file
+}
+
+struct Transition {
+  from: CameraState,
+  to: CameraState,
+  elapsed: f32,
+}
+
+// Saved viewpoints that can be restored with a smooth transition
+pub struct Bookmarks {
+  slots: [Option<CameraState>; BOOKMARK_SLOTS],
+  transition: Option<Transition>,
+}
+
+impl Bookmarks {
+  pub fn new() -> Self {
+    Bookmarks {
+      slots: [None; BOOKMARK_SLOTS],
+      transition: None,
+    }
+  }
+
+  pub fn store(&mut self, slot: usize, camera: &Camera) {
+    if slot < BOOKMARK_SLOTS {
+      self.slots[slot] = Some(camera.state());
+    }
+  }
+
+  // Starts moving the camera towards a saved slot, returns false if it is empty
+  pub fn restore(&mut self, slot: usize, camera: &Camera) -> bool {
+    match self.slots.get(slot).cloned().flatten() {
+      Some(target) => {
+        self.transition = Some(Transition {
+          from: camera.state(),
+          to: target,
+          elapsed: 0.0,
+        });
+        true
+      }
+      None => false,
+    }
+  }
+
+  // Path through the stored viewpoints in slot order, skipping empty slots
+  pub fn path(&self) -> Option<Path> {
+    Path::new(self.slots.iter().flatten().copied().collect())
+  }
+
+  // Advances the active transition by `dt` seconds
+  pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+    let Some(transition) = self.transition.as_mut() else {
+      return;
+    };
+
+    transition.elapsed = (transition.elapsed + dt).min(BOOKMARK_TRANSITION_SECONDS);
+    let t = transition.elapsed / BOOKMARK_TRANSITION_SECONDS;
+    let t = t * t * (3.0 - 2.0 * t); // smoothstep
+
+    camera.set_state(&CameraState {
+      eye: lerp(&transition.from.eye, &transition.to.eye, t),
+      center: lerp(&transition.from.center, &transition.to.center, t),
+      up: lerp(&transition.from.up, &transition.to.up, t).normalize(),
+    });
+
+    if transition.elapsed >= BOOKMARK_TRANSITION_SECONDS {
+      self.transition = None;
+    }
+  }
+}
+
+impl Default for Bookmarks {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// Catmull-Rom spline through camera keyframes; only eye and center are
+// interpolated, `up` is left to the camera
+pub struct Path {
+  keyframes: Vec<CameraState>,
+}
+
+impl Path {
+  // None without keyframes, since there would be nothing to sample
+  pub fn new(keyframes: Vec<CameraState>) -> Option<Self> {
+    if keyframes.is_empty() {
+      None
+    } else {
+      Some(Path { keyframes })
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.keyframes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.keyframes.is_empty()
+  }
+
+  // Eye and center at `t` in 0..1 over the whole path, passing through every
+  // keyframe. The first and last keyframes are repeated as the outer control
+  // points, so paths of one to three keyframes work as well.
+  pub fn sample(&self, t: f32) -> (Vec3, Vec3) {
+    let last = self.keyframes.len() - 1;
+    if last == 0 {
+      return (self.keyframes[0].eye, self.keyframes[0].center);
+    }
+
+    let position = t.clamp(0.0, 1.0) * last as f32;
+    let segment = (position.floor() as usize).min(last - 1);
+    let local = position - segment as f32;
+
+    let key = |index: usize| &self.keyframes[index.min(last)];
+    let (k0, k1, k2, k3) = (key(segment.saturating_sub(1)), key(segment), key(segment + 1), key(segment + 2));
+
+    (
+      catmull_rom(&k0.eye, &k1.eye, &k2.eye, &k3.eye, local),
+      catmull_rom(&k0.center, &k1.center, &k2.center, &k3.center, local),
+    )
+  }
+}
+
+// Uniform Catmull-Rom between p1 (t = 0) and p2 (t = 1)
+fn catmull_rom(p0: &Vec3, p1: &Vec3, p2: &Vec3, p3: &Vec3, t: f32) -> Vec3 {
+  let t2 = t * t;
+  let t3 = t2 * t;
+  (p1 * 2.0
+    + (p2 - p0) * t
+    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+    * 0.5
+}
+
+// Plays a Path over `duration` seconds, driving the camera each frame
+pub struct Tour {
+  path: Path,
+  duration: f32,
+  elapsed: f32,
+  pub paused: bool,
+}
+
+impl Tour {
+  pub fn new(path: Path, duration: f32) -> Self {
+    Tour {
+      path,
+      duration: duration.max(f32::EPSILON),
+      elapsed: 0.0,
+      paused: false,
+    }
+  }
+
+  // Moves the camera `dt` seconds further along the path; returns false once
+  // the last keyframe has been reached, so the caller can drop the tour
+  pub fn update(&mut self, camera: &mut Camera, dt: f32) -> bool {
+    if !self.paused {
+      self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    let (eye, center) = self.path.sample(self.elapsed / self.duration);
+    camera.eye = eye;
+    camera.center = center;
+    camera.has_changed = true;
+
+    self.elapsed < self.duration
+  }
+}
+
+// What the left mouse button did this frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseGesture {
+  Idle,
+  // Cursor motion in pixels since the previous frame, Y pointing down
+  Drag(f32, f32),
+  // Released without moving enough to count as a drag
+  Click,
+}
+
+// Tracks a left-button drag across frames. The starting position is taken
+// on the frame the button goes down, so pressing never makes the view jump.
+#[derive(Default)]
+pub struct MouseDrag {
+  previous: Option<(f32, f32)>,
+  travelled: f32,
+}
+
+impl MouseDrag {
+  // Feeds this frame's button state and cursor position, if the cursor is
+  // known; a lost cursor keeps the last position instead of jumping later
+  pub fn update(&mut self, down: bool, position: Option<(f32, f32)>) -> MouseGesture {
+    if !down {
+      let pressed = self.previous.take().is_some();
+      let travelled = std::mem::take(&mut self.travelled);
+      return if pressed && travelled < CLICK_SLOP { MouseGesture::Click } else { MouseGesture::Idle };
+    }
+
+    let Some(current) = position else {
+      return MouseGesture::Drag(0.0, 0.0);
+    };
+    let previous = self.previous.replace(current).unwrap_or(current);
+    let (dx, dy) = (current.0 - previous.0, current.1 - previous.1);
+    self.travelled += (dx * dx + dy * dy).sqrt();
+    MouseGesture::Drag(dx, dy)
+  }
+}
+
+// Turns a drag of dx, dy pixels into the (delta_yaw, delta_pitch) taken by
+// both `orbit` and `look`, `sensitivity` radians per pixel. Orbiting, the
+// scene follows the hand; flying, the view turns towards the drag.
+pub fn drag_angles(dx: f32, dy: f32, sensitivity: f32) -> (f32, f32) {
+  (dx * sensitivity, -dy * sensitivity)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::uniforms::create_view_matrix;
+  use nalgebra_glm::Vec4;
+
+  fn test_camera() -> Camera {
+    Camera::new(Vec3::new(0.0, 0.0, 20.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0))
+  }
+
+  // World up as seen by the camera: positive while the view is upright
+  fn screen_up(camera: &Camera) -> f32 {
+    let view = create_view_matrix(camera.eye, camera.center, camera.up);
+    (view * Vec4::new(0.0, 1.0, 0.0, 0.0)).y
+  }
+
+  // Angle of the eye above the center's horizontal plane
+  fn elevation(camera: &Camera) -> f32 {
+    let offset = camera.eye - camera.center;
+    offset.y.atan2((offset.x * offset.x + offset.z * offset.z).sqrt())
+  }
+
+  #[test]
+  fn orbiting_over_the_pole_stops_without_inverting() {
+    let mut camera = test_camera();
+    // W orbits with negative pitch, which raises the eye
+    for _ in 0..200 {
+      camera.orbit(0.0, -PI / 50.0);
+      assert!(camera.eye.y > 0.0);
+      assert!(screen_up(&camera) > 0.0);
+    }
+
+    let offset = camera.eye - camera.center;
+    assert!((elevation(&camera) - MAX_ORBIT_PITCH).abs() < 1e-3);
+    assert!((camera.distance() - 20.0).abs() < 1e-3);
+    // Still on the side it started from, not swung over to the back
+    assert!(offset.z > 0.0 && offset.x.abs() < 1e-3);
+  }
+
+  fn keyframe(eye: Vec3) -> CameraState {
+    CameraState { eye, center: Vec3::new(0.0, 0.0, 0.0), up: Vec3::new(0.0, 1.0, 0.0) }
+  }
+
+  fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!((actual - expected).magnitude() < 1e-4, "expected {:?}, got {:?}", expected, actual);
+  }
+
+  #[test]
+  fn path_passes_through_every_keyframe() {
+    let eyes = [Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 5.0, 10.0), Vec3::new(-10.0, 0.0, 0.0), Vec3::new(0.0, -5.0, -10.0)];
+    let path = Path::new(eyes.iter().map(|&eye| keyframe(eye)).collect()).unwrap();
+    for (index, eye) in eyes.iter().enumerate() {
+      let (sampled, center) = path.sample(index as f32 / 3.0);
+      assert_near(sampled, *eye);
+      assert_near(center, Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    // Outside 0..1 the path holds its ends
+    assert_near(path.sample(-1.0).0, eyes[0]);
+    assert_near(path.sample(2.0).0, eyes[3]);
+  }
+
+  #[test]
+  fn short_paths_clamp_their_endpoints() {
+    assert!(Path::new(Vec::new()).is_none());
+
+    let single = Path::new(vec![keyframe(Vec3::new(1.0, 2.0, 3.0))]).unwrap();
+    assert_near(single.sample(0.7).0, Vec3::new(1.0, 2.0, 3.0));
+
+    // Two keyframes: the repeated end points leave a straight segment
+    let (start, end) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 0.0));
+    let pair = Path::new(vec![keyframe(start), keyframe(end)]).unwrap();
+    assert_near(pair.sample(0.0).0, start);
+    assert_near(pair.sample(1.0).0, end);
+    let middle = pair.sample(0.5).0;
+    assert!(middle.y.abs() < 1e-5 && middle.z.abs() < 1e-5 && (0.0..4.0).contains(&middle.x));
+  }
+
+  #[test]
+  fn tour_stops_at_the_last_keyframe_and_pauses() {
+    let end = Vec3::new(0.0, 0.0, 30.0);
+    let path = Path::new(vec![keyframe(Vec3::new(0.0, 0.0, 10.0)), keyframe(end)]).unwrap();
+    let mut tour = Tour::new(path, 2.0);
+    let mut camera = test_camera();
+
+    assert!(tour.update(&mut camera, 0.5));
+    tour.paused = true;
+    let held = camera.eye;
+    assert!(tour.update(&mut camera, 0.5));
+    assert_near(camera.eye, held);
+
+    tour.paused = false;
+    assert!(tour.update(&mut camera, 0.5));
+    assert!(tour.update(&mut camera, 0.5));
+    // A long frame overshoots the end, which still lands on the last keyframe
+    assert!(!tour.update(&mut camera, 5.0));
+    assert_near(camera.eye, end);
+  }
+
+  #[test]
+  fn bookmark_transition_takes_the_same_time_at_any_frame_rate() {
+    let target = Vec3::new(0.0, 10.0, 20.0);
+    let arrival = |dt: f32| {
+      let mut camera = test_camera();
+      let mut bookmarks = Bookmarks::new();
+      camera.eye = target;
+      bookmarks.store(0, &camera);
+      camera.eye = Vec3::new(0.0, 0.0, 30.0);
+      assert!(bookmarks.restore(0, &camera));
+
+      let mut seconds = 0.0;
+      while bookmarks.transition.is_some() {
+        bookmarks.update(&mut camera, dt);
+        seconds += dt;
+      }
+      assert_near(camera.eye, target);
+      seconds
+    };
+
+    // Within one frame of the transition length, at 30 and at 120 fps
+    for dt in [1.0 / 30.0, 1.0 / 120.0] {
+      let seconds = arrival(dt);
+      assert!((seconds - BOOKMARK_TRANSITION_SECONDS).abs() <= dt + 1e-4, "arrived after {}s at dt {}", seconds, dt);
+    }
+  }
+
+  #[test]
+  fn one_huge_orbit_step_is_clamped() {
+    let mut camera = test_camera();
+    camera.orbit(0.0, -10.0);
+    assert!(elevation(&camera) <= MAX_ORBIT_PITCH + 1e-4);
+    assert!(camera.eye.y > camera.center.y);
+    assert!(screen_up(&camera) > 0.0);
+
+    camera.orbit(0.0, 25.0);
+    assert!(elevation(&camera) >= -MAX_ORBIT_PITCH - 1e-4);
+    assert!(camera.eye.y < camera.center.y);
+    assert!((camera.distance() - 20.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn flying_forward_approaches_and_strafing_is_sideways() {
+    let mut camera = test_camera();
+    let ahead = Vec3::new(0.0, 0.0, -5.0);
+    let before = (ahead - camera.eye).magnitude();
+    camera.move_forward(3.0);
+    assert!((ahead - camera.eye).magnitude() < before);
+
+    let forward = (camera.center - camera.eye).normalize();
+    let start = camera.eye;
+    camera.move_right(2.0);
+    let strafe = camera.eye - start;
+    assert!((strafe.magnitude() - 2.0).abs() < 1e-5);
+    assert!(strafe.dot(&forward).abs() < 1e-5);
+    // Right of a camera looking down -Z is +X
+    assert!(strafe.x > 0.0);
+  }
+
+  #[test]
+  fn drag_angles_follow_the_hand() {
+    // Right and up on screen (Y grows downwards) give positive yaw and pitch
+    assert_eq!(drag_angles(10.0, -20.0, 0.5), (5.0, 10.0));
+    assert_eq!(drag_angles(-4.0, 4.0, 0.25), (-1.0, -1.0));
+    assert_eq!(drag_angles(0.0, 0.0, 0.5), (0.0, 0.0));
+  }
+
+  #[test]
+  fn mouse_drag_reports_motion_and_clicks() {
+    let mut drag = MouseDrag::default();
+    // The press itself never moves the view
+    assert_eq!(drag.update(true, Some((100.0, 50.0))), MouseGesture::Drag(0.0, 0.0));
+    assert_eq!(drag.update(true, Some((110.0, 45.0))), MouseGesture::Drag(10.0, -5.0));
+    assert_eq!(drag.update(false, Some((110.0, 45.0))), MouseGesture::Idle);
+
+    // Barely moving before release counts as a click
+    drag.update(true, Some((0.0, 0.0)));
+    drag.update(true, Some((1.0, 1.0)));
+    assert_eq!(drag.update(false, None), MouseGesture::Click);
+    assert_eq!(drag.update(false, None), MouseGesture::Idle);
+  }
+}
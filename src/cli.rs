@@ -0,0 +1,137 @@
+use std::fmt;
+use std::str::FromStr;
+
+pub const USAGE: &str =
+    "usage: Model3D [--model <file.obj>] [--flip-winding] [--crater-seed <n>] [--crater-count <n>] \
+     [--width <px>] [--height <px>] [--fullscreen]";
+
+// Window and framebuffer size when --width or --height is left out
+pub const DEFAULT_WIDTH: usize = 800;
+pub const DEFAULT_HEIGHT: usize = 600;
+
+// Options given on the command line; anything left out keeps the default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    // Mesh drawn for every body instead of the scene's own meshes
+    pub model: Option<String>,
+    // Reverse the triangle winding (and normals) of the --model mesh
+    pub flip_winding: bool,
+    // Crater layout of the rocky planets, see crater::generate_craters
+    pub crater_seed: Option<u64>,
+    pub crater_count: Option<usize>,
+    // Initial window and framebuffer size in pixels, never zero
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    // Borderless window covering the screen from its top-left corner
+    pub fullscreen: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgsError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    Unknown(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::MissingValue(flag) => write!(f, "{} needs a value", flag),
+            ArgsError::InvalidValue(flag, value) => write!(f, "invalid value `{}` for {}", value, flag),
+            ArgsError::Unknown(arg) => write!(f, "unknown argument `{}`", arg),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Args {
+    // Parses the arguments after the program name. Options with a value
+    // accept both `--model <path>` and `--model=<path>`.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+
+            match flag.as_str() {
+                "--model" => parsed.model = Some(value(&flag, inline, &mut args)?),
+                "--flip-winding" if inline.is_none() => parsed.flip_winding = true,
+                "--crater-seed" => parsed.crater_seed = Some(number(&flag, inline, &mut args)?),
+                "--crater-count" => parsed.crater_count = Some(number(&flag, inline, &mut args)?),
+                "--width" => parsed.width = Some(dimension(&flag, inline, &mut args)?),
+                "--height" => parsed.height = Some(dimension(&flag, inline, &mut args)?),
+                "--fullscreen" if inline.is_none() => parsed.fullscreen = true,
+                _ => return Err(ArgsError::Unknown(arg)),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width.unwrap_or(DEFAULT_WIDTH)
+    }
+
+    pub fn height(&self) -> usize {
+        self.height.unwrap_or(DEFAULT_HEIGHT)
+    }
+}
+
+// The inline `=value` if there was one, otherwise the next argument
+fn value(flag: &str, inline: Option<String>, args: &mut impl Iterator<Item = String>) -> Result<String, ArgsError> {
+    inline.or_else(|| args.next())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))
+}
+
+fn number<T: FromStr>(flag: &str, inline: Option<String>, args: &mut impl Iterator<Item = String>) -> Result<T, ArgsError> {
+    let value = value(flag, inline, args)?;
+    value.parse().map_err(|_| ArgsError::InvalidValue(flag.to_string(), value))
+}
+
+// A size in pixels: like `number`, but zero is rejected too
+fn dimension(flag: &str, inline: Option<String>, args: &mut impl Iterator<Item = String>) -> Result<usize, ArgsError> {
+    let value = value(flag, inline, args)?;
+    match value.parse() {
+        Ok(pixels) if pixels > 0 => Ok(pixels),
+        _ => Err(ArgsError::InvalidValue(flag.to_string(), value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_when_nothing_is_given() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args, Args::default());
+        assert_eq!((args.width(), args.height()), (DEFAULT_WIDTH, DEFAULT_HEIGHT));
+    }
+
+    #[test]
+    fn explicit_values_in_both_forms() {
+        let args = parse(&["--width", "1280", "--height=720", "--model=ship.obj", "--crater-seed", "7", "--fullscreen"]).unwrap();
+        assert_eq!((args.width(), args.height()), (1280, 720));
+        assert_eq!(args.model.as_deref(), Some("ship.obj"));
+        assert_eq!(args.crater_seed, Some(7));
+        assert!(args.fullscreen && !args.flip_winding);
+    }
+
+    #[test]
+    fn zero_and_malformed_values_are_rejected() {
+        assert_eq!(parse(&["--width", "0"]), Err(ArgsError::InvalidValue("--width".to_string(), "0".to_string())));
+        assert!(matches!(parse(&["--height=abc"]), Err(ArgsError::InvalidValue(..))));
+        assert_eq!(parse(&["--width"]), Err(ArgsError::MissingValue("--width".to_string())));
+        assert_eq!(parse(&["--zoom"]), Err(ArgsError::Unknown("--zoom".to_string())));
+    }
+}
@@ -0,0 +1,103 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::uniforms::NEAR_PLANE;
+use crate::vertex::Vertex;
+
+// Position of a vertex relative to the near plane in clip space; with the
+// perspective matrices of this crate w is the distance in front of the
+// camera, so the plane is w = NEAR_PLANE and positive values are visible.
+// NaN (from a broken transform) counts as outside.
+fn near_distance(vertex: &Vertex) -> f32 {
+    let distance = vertex.clip_position.w - NEAR_PLANE;
+    if distance.is_nan() { -1.0 } else { distance }
+}
+
+// True when every vertex is past the near plane, so the triangle needs no
+// clipping at all (the common case)
+pub fn past_near_plane(triangle: &[Vertex]) -> bool {
+    triangle.iter().all(|vertex| near_distance(vertex) >= 0.0)
+}
+
+// Clips a triangle of vertex-shaded vertices against the near plane before
+// the perspective divide (Sutherland-Hodgman with a single plane). Returns
+// zero triangles when it lies entirely in front of the plane, otherwise one
+// or two that cover its visible part. New vertices interpolate every
+// attribute at the plane and get their screen position from
+// `viewport_matrix`, so all output vertices have w >= NEAR_PLANE.
+pub fn clip_near(triangle: &[Vertex], viewport_matrix: &Mat4) -> Vec<[Vertex; 3]> {
+    let mut polygon: Vec<Vertex> = Vec::with_capacity(4);
+
+    for (index, current) in triangle.iter().enumerate() {
+        let next = &triangle[(index + 1) % triangle.len()];
+        let (current_distance, next_distance) = (near_distance(current), near_distance(next));
+
+        if current_distance >= 0.0 {
+            polygon.push(current.clone());
+        }
+        // The edge crosses the plane: add the crossing point
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            let mut crossing = current.lerp(next, t);
+            crossing.transformed_position = project(&crossing.clip_position, viewport_matrix);
+            polygon.push(crossing);
+        }
+    }
+
+    // Fan the convex polygon (3 or 4 vertices) back into triangles
+    (1..polygon.len().saturating_sub(1))
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+// Perspective divide and viewport mapping, as in the vertex shader
+fn project(clip_position: &Vec4, viewport_matrix: &Mat4) -> Vec3 {
+    let ndc = clip_position / clip_position.w;
+    let screen = viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+    Vec3::new(screen.x, screen.y, screen.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+    use crate::uniforms::create_viewport_matrix;
+
+    // A vertex with only its clip-space position set, as clip_near reads it
+    fn clip_vertex(x: f32, y: f32, w: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+        vertex.clip_position = Vec4::new(x, y, 0.0, w);
+        vertex
+    }
+
+    fn assert_visible(pieces: &[[Vertex; 3]]) {
+        for vertex in pieces.iter().flatten() {
+            assert!(vertex.clip_position.w >= NEAR_PLANE - 1e-5, "w = {}", vertex.clip_position.w);
+            assert!(vertex.transformed_position.iter().all(|value| value.is_finite()));
+        }
+    }
+
+    #[test]
+    fn straddling_triangle_is_cut_at_the_near_plane() {
+        let viewport = create_viewport_matrix(800.0, 600.0);
+
+        // One vertex behind the camera: the visible part is a quad
+        let one_behind = [clip_vertex(-1.0, -1.0, 5.0), clip_vertex(1.0, -1.0, 5.0), clip_vertex(0.0, 1.0, -2.0)];
+        assert!(!past_near_plane(&one_behind));
+        let pieces = clip_near(&one_behind, &viewport);
+        assert_eq!(pieces.len(), 2);
+        assert_visible(&pieces);
+
+        // Two behind: a single smaller triangle
+        let two_behind = [clip_vertex(-1.0, -1.0, 5.0), clip_vertex(1.0, -1.0, -3.0), clip_vertex(0.0, 1.0, -2.0)];
+        let pieces = clip_near(&two_behind, &viewport);
+        assert_eq!(pieces.len(), 1);
+        assert_visible(&pieces);
+    }
+
+    #[test]
+    fn triangle_behind_the_camera_is_dropped() {
+        let viewport = create_viewport_matrix(800.0, 600.0);
+        let behind = [clip_vertex(-1.0, -1.0, -1.0), clip_vertex(1.0, -1.0, -1.0), clip_vertex(0.0, 1.0, f32::NAN)];
+        assert!(clip_near(&behind, &viewport).is_empty());
+    }
+}
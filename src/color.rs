@@ -27,6 +27,43 @@ impl Color {
     }
   }
 
+  // Approximate color of a blackbody at `kelvin` (Tanner Helland's fit),
+  // from red at 1000K through white near 6500K to blue at 40000K
+  pub fn from_temperature(kelvin: f32) -> Self {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let r = if t <= 66.0 {
+      255.0
+    } else {
+      329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+
+    let g = if t <= 66.0 {
+      99.470_8 * t.ln() - 161.119_57
+    } else {
+      288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+
+    let b = if t >= 66.0 {
+      255.0
+    } else if t <= 19.0 {
+      0.0
+    } else {
+      138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    Color {
+      r: r.clamp(0.0, 255.0) as u8,
+      g: g.clamp(0.0, 255.0) as u8,
+      b: b.clamp(0.0, 255.0) as u8,
+    }
+  }
+
+  // Channels as f32 in 0.0..1.0, the inverse of `from_float`
+  pub fn to_float(&self) -> [f32; 3] {
+    [self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0]
+  }
+
   // Function to create a color from a hex value
   pub fn from_hex(hex: u32) -> Self {
     let r = ((hex >> 16) & 0xFF) as u8;
@@ -50,6 +87,61 @@ impl Color {
     }
   }
 
+  // Like `lerp`, without rounding the result back to u8; channels in 0.0..1.0
+  pub fn lerp_float(&self, other: &Color, t: f32) -> [f32; 3] {
+    let t = t.clamp(0.0, 1.0);
+    let (from, to) = (self.to_float(), other.to_float());
+    [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t)
+  }
+
+  // Barycentric blend of three colors (weights summing to 1), rounded
+  pub fn interpolate(a: &Color, b: &Color, c: &Color, w1: f32, w2: f32, w3: f32) -> Color {
+    let mix = |x: u8, y: u8, z: u8| -> u8 {
+      (x as f32 * w1 + y as f32 * w2 + z as f32 * w3).round().clamp(0.0, 255.0) as u8
+    };
+    Color {
+      r: mix(a.r, b.r, c.r),
+      g: mix(a.g, b.g, c.g),
+      b: mix(a.b, b.b, c.b),
+    }
+  }
+
+  // Tonal adjustment on channels normalized to 0..1: contrast scales around
+  // 0.5, then brightness - 1.0 is added, so (1.0, 1.0) leaves the color as is.
+  // Results saturate at black and white.
+  pub fn adjust(&self, brightness: f32, contrast: f32) -> Color {
+    let offset = brightness - 1.0;
+    let map = |channel: u8| -> u8 {
+      let v = (channel as f32 / 255.0 - 0.5) * contrast + 0.5 + offset;
+      (v.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    Color {
+      r: map(self.r),
+      g: map(self.g),
+      b: map(self.b),
+    }
+  }
+
+  // Per-channel mean, summed in u32 so many samples cannot overflow;
+  // black for an empty slice
+  pub fn average(colors: &[Color]) -> Color {
+    if colors.is_empty() {
+      return Color::black();
+    }
+
+    let count = colors.len() as u32;
+    let (r, g, b) = colors.iter().fold((0u32, 0u32, 0u32), |(r, g, b), color| {
+      (r + color.r as u32, g + color.g as u32, b + color.b as u32)
+    });
+
+    // Rounded to the nearest value instead of truncated
+    Color {
+      r: ((r + count / 2) / count) as u8,
+      g: ((g + count / 2) / count) as u8,
+      b: ((b + count / 2) / count) as u8,
+    }
+  }
+
   pub fn is_black(&self) -> bool {
     self.r == 0 && self.g == 0 && self.b == 0 
   }
@@ -76,9 +168,9 @@ impl Color {
   }
 
   pub fn blend_subtract(&self, blend: &Color) -> Color {
-    let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-    let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-    let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
+    let r = (self.r as i16 - blend.r as i16).clamp(0, 255) as u8;
+    let g = (self.g as i16 - blend.g as i16).clamp(0, 255) as u8;
+    let b = (self.b as i16 - blend.b as i16).clamp(0, 255) as u8;
 
     Color::new(r, g, b)
   }
@@ -93,6 +185,46 @@ impl Color {
 
 }
 
+// Palette of colors placed at positions in 0..1, sampled by interpolation
+pub struct Gradient {
+  stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+  pub fn new(mut stops: Vec<(f32, Color)>) -> Self {
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Gradient { stops }
+  }
+
+  // Values outside the first and last stop clamp to their colors
+  pub fn sample(&self, t: f32) -> Color {
+    let (first, last) = match (self.stops.first(), self.stops.last()) {
+      (Some(first), Some(last)) => (first, last),
+      _ => return Color::black(),
+    };
+
+    if t <= first.0 {
+      return first.1;
+    }
+    if t >= last.0 {
+      return last.1;
+    }
+
+    for pair in self.stops.windows(2) {
+      let (start, end) = (pair[0], pair[1]);
+      if t <= end.0 {
+        let span = end.0 - start.0;
+        if span <= 0.0 {
+          return end.1;
+        }
+        return start.1.lerp(&end.1, (t - start.0) / span);
+      }
+    }
+
+    last.1
+  }
+}
+
 // Implement addition for Color
 use std::ops::Add;
 
@@ -128,4 +260,59 @@ impl fmt::Display for Color {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
   }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn daylight_temperature_is_near_white() {
+    let color = Color::from_temperature(6500.0);
+    let high = color.r.max(color.g).max(color.b);
+    let low = color.r.min(color.g).min(color.b);
+    assert!(low >= 230, "6500K should be bright on every channel, got {}", color);
+    assert!(high - low <= 25, "6500K should be close to neutral, got {}", color);
+  }
+
+  #[test]
+  fn incandescent_temperature_is_warm() {
+    let color = Color::from_temperature(3000.0);
+    assert!(color.r > color.g && color.g > color.b, "3000K should fall off from red to blue, got {}", color);
+    assert!(color.r as i32 - color.b as i32 >= 80, "3000K should be clearly orange, got {}", color);
+  }
+
+  #[test]
+  fn scaling_by_a_factor_saturates() {
+    let color = Color::new(200, 100, 0);
+    assert_eq!((color * 0.0).to_hex(), 0x000000);
+    assert_eq!((color * 1.0).to_hex(), color.to_hex());
+    assert_eq!((color * 2.0).to_hex(), Color::new(255, 200, 0).to_hex());
+  }
+
+  #[test]
+  fn average_of_black_and_white_is_mid_gray() {
+    let gray = Color::average(&[Color::black(), Color::new(255, 255, 255)]);
+    for channel in [gray.r, gray.g, gray.b] {
+      assert!(channel == 127 || channel == 128, "expected mid-gray, got {}", gray);
+    }
+    assert!(Color::average(&[]).is_black());
+  }
+
+  #[test]
+  fn adjust_identity_and_extremes() {
+    let color = Color::new(12, 128, 240);
+    assert_eq!(color.adjust(1.0, 1.0).to_hex(), color.to_hex());
+
+    // Strong brightness or contrast pins every channel to black or white
+    assert_eq!(color.adjust(3.0, 1.0).to_hex(), 0xFFFFFF);
+    assert_eq!(color.adjust(-1.0, 1.0).to_hex(), 0x000000);
+    assert_eq!(Color::new(20, 100, 200).adjust(1.0, 50.0).to_hex(), 0x0000FF);
+  }
+
+  #[test]
+  fn adding_and_brightening_clamp_at_white() {
+    let sum = Color::new(200, 200, 200) + Color::new(100, 100, 100);
+    assert_eq!(sum.to_hex(), 0xFFFFFF);
+    assert_eq!((Color::new(100, 150, 200) * 1.5).to_hex(), Color::new(150, 225, 255).to_hex());
+  }
+}
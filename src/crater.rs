@@ -0,0 +1,53 @@
+use nalgebra_glm::Vec3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub const DEFAULT_CRATER_SEED: u64 = 2024;
+pub const DEFAULT_CRATER_COUNT: usize = 48;
+
+// Radius range, as chord length on the unit sphere
+const MIN_CRATER_RADIUS: f32 = 0.03;
+const MAX_CRATER_RADIUS: f32 = 0.3;
+
+// Bowl on the unit sphere: `center` is a unit direction from the body center
+#[derive(Clone, Copy, Debug)]
+pub struct Crater {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+// The same seed and count always give the same craters. Centers are uniform
+// over the sphere; radii follow a power law, so small craters outnumber big ones.
+pub fn generate_craters(seed: u64, count: usize) -> Vec<Crater> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| {
+        let z: f32 = rng.gen_range(-1.0..1.0);
+        let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+        let ring = (1.0 - z * z).sqrt();
+        let center = Vec3::new(ring * angle.cos(), z, ring * angle.sin());
+
+        let size: f32 = rng.gen();
+        let radius = MIN_CRATER_RADIUS + (MAX_CRATER_RADIUS - MIN_CRATER_RADIUS) * size * size * size;
+        Crater { center, radius }
+    }).collect()
+}
+
+// Floor and rim weights (each 0..1) of the crater covering `direction`, a
+// unit vector; the smallest relative distance wins where craters overlap
+pub fn crater_profile(craters: &[Crater], direction: &Vec3) -> (f32, f32) {
+    let nearest = craters.iter()
+        .map(|crater| (direction - crater.center).magnitude() / crater.radius)
+        .fold(f32::INFINITY, f32::min);
+    if nearest >= 1.25 {
+        return (0.0, 0.0);
+    }
+
+    let floor = 1.0 - smoothstep(0.6, 0.85, nearest);
+    let rim = smoothstep(0.7, 0.95, nearest) * (1.0 - smoothstep(1.0, 1.25, nearest));
+    (floor, rim)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -1,31 +1,41 @@
 use nalgebra_glm::{Vec3, Vec2};
 use crate::color::Color;
 
+// Output of the rasterizer, one per covered pixel. All fields are
+// filled by `triangle()` from the three vertices of the primitive.
 pub struct Fragment {
-    pub position: Vec2,
-    pub color: Color,
-    pub depth: f32,
-    pub normal: Vec3,
-    pub intensity: f32,
-    pub vertex_position: Vec3,
+    pub position: Vec2,        // pixel coordinates in the framebuffer
+    pub color: Color,          // interpolated vertex color, white when the model has none
+    pub depth: f32,            // 0 at the near plane, 1 at the far plane; smaller is closer
+    pub normal: Vec3,          // interpolated transformed_normal, normalized
+    pub tangent: Vec3,         // interpolated transformed_tangent, orthogonal to normal
+    pub tex_coords: Vec2,      // interpolated texture coordinates, zero when the model has none
+    pub world_position: Vec3,  // interpolated world-space position, used for lighting
+    pub vertex_position: Vec3, // interpolated object-space position, used for noise lookups
 }
 
 impl Fragment {
+    // Arguments in field order; every attribute is already interpolated
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vec2,
         color: Color,
         depth: f32,
         normal: Vec3,
-        intensity: f32,
+        tangent: Vec3,
+        tex_coords: Vec2,
+        world_position: Vec3,
         vertex_position: Vec3,
-    ) -> Self {  
+    ) -> Self {
         Fragment {
             position,
             color,
             depth,
             normal,
-            intensity,
+            tangent,
+            tex_coords,
+            world_position,
             vertex_position,
         }
     }
-}
\ No newline at end of file
+}
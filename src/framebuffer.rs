@@ -1,12 +1,53 @@
 // framebuffer.rs
 
+use std::io;
+
+use crate::color::{Color, Gradient};
+
+// 4x4 ordered dithering matrix
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+// Fragment count per pixel that saturates the overdraw heatmap to red
+const OVERDRAW_SATURATION: f32 = 8.0;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    // Fragments that reached each pixel since the last clear
+    overdraw: Vec<u32>,
+    // Object ID of the closest fragment per pixel, 0 where nothing was drawn
+    pub id_buffer: Vec<u32>,
+    // Self-emitted light of the closest fragment per pixel (0 for lit
+    // surfaces and the background), the bright-source mask for glow effects
+    pub emission_buffer: Vec<f32>,
+    // Optional linear RGB buffer (1.0 = full channel, may go above) written
+    // alongside `buffer`; `resolve_to_u32` quantizes it back into `buffer`
+    hdr: Option<Vec<[f32; 3]>>,
     background_color: u32,
     current_color: u32,
+    current_hdr: [f32; 3],
+    current_id: u32,
+    current_emission: f32,
+}
+
+fn unpack(color: u32) -> [f32; 3] {
+    [
+        ((color >> 16) & 0xFF) as f32 / 255.0,
+        ((color >> 8) & 0xFF) as f32 / 255.0,
+        (color & 0xFF) as f32 / 255.0,
+    ]
+}
+
+fn pack(rgb: [f32; 3]) -> u32 {
+    let [r, g, b] = rgb.map(|channel| (channel * 255.0).round().clamp(0.0, 255.0) as u32);
+    (r << 16) | (g << 8) | b
 }
 
 impl Framebuffer {
@@ -16,11 +57,29 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            overdraw: vec![0; width * height],
+            id_buffer: vec![0; width * height],
+            emission_buffer: vec![0.0; width * height],
+            hdr: None,
             background_color: 0x000000,
             current_color: 0xFFFFFF,
+            current_hdr: [1.0; 3],
+            current_id: 0,
+            current_emission: 0.0,
         }
     }
 
+    // Reallocates every per-pixel buffer for the new size. The contents are
+    // cleared to the background; the background color and HDR mode carry over.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let hdr = self.is_hdr();
+        let background_color = self.background_color;
+        *self = Framebuffer::new(width, height);
+        self.background_color = background_color;
+        self.clear();
+        self.set_hdr(hdr);
+    }
+
     pub fn clear(&mut self) {
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
@@ -28,23 +87,568 @@ impl Framebuffer {
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
+        for count in self.overdraw.iter_mut() {
+            *count = 0;
+        }
+        for id in self.id_buffer.iter_mut() {
+            *id = 0;
+        }
+        self.emission_buffer.fill(0.0);
+        if let Some(hdr) = &mut self.hdr {
+            hdr.fill(unpack(self.background_color));
+        }
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
+            self.overdraw[index] += 1;
             if self.zbuffer[index] > depth {
                 self.buffer[index] = self.current_color;
                 self.zbuffer[index] = depth;
+                self.id_buffer[index] = self.current_id;
+                self.emission_buffer[index] = self.current_emission;
+                if let Some(hdr) = &mut self.hdr {
+                    hdr[index] = self.current_hdr;
+                }
+            }
+        }
+    }
+
+    // Depth test and depth write only, for passes that must hide what is
+    // drawn after them without showing anything themselves
+    pub fn depth_point(&mut self, x: usize, y: usize, depth: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > depth {
+                self.zbuffer[index] = depth;
+            }
+        }
+    }
+
+    // Translucent counterpart of `point`: if the fragment passes the depth
+    // test, the current color is mixed over the pixel as
+    // dst * (1 - alpha) + src * alpha. Depth, object ID and emission are left
+    // untouched, so draw translucent surfaces after everything behind them.
+    pub fn blend(&mut self, x: usize, y: usize, depth: f32, alpha: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            self.overdraw[index] += 1;
+            if self.zbuffer[index] > depth {
+                let alpha = alpha.clamp(0.0, 1.0);
+                let mix = |dst: [f32; 3], src: [f32; 3]| -> [f32; 3] {
+                    [0, 1, 2].map(|i| dst[i] * (1.0 - alpha) + src[i] * alpha)
+                };
+                self.buffer[index] = pack(mix(unpack(self.buffer[index]), unpack(self.current_color)));
+                if let Some(hdr) = &mut self.hdr {
+                    hdr[index] = mix(hdr[index], self.current_hdr);
+                }
+            }
+        }
+    }
+
+    // Bresenham line in the current color, drawn on top of everything
+    // (no depth test). Pixels outside the buffer are skipped.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+
+        loop {
+            if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+                let index = y as usize * self.width + x as usize;
+                self.buffer[index] = self.current_color;
+            }
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
             }
         }
     }
 
+    // Writes the color buffer as an RGB PNG, dropping the unused high byte
+    pub fn save_png(&self, path: &str) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 3);
+        for pixel in &self.buffer {
+            bytes.push(((pixel >> 16) & 0xFF) as u8);
+            bytes.push(((pixel >> 8) & 0xFF) as u8);
+            bytes.push((pixel & 0xFF) as u8);
+        }
+
+        image::save_buffer(
+            path,
+            &bytes,
+            self.width as u32,
+            self.height as u32,
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(io::Error::other)
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
 
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
+        self.current_hdr = unpack(color);
+    }
+
+    // Like `set_current_color`, but keeps values above 1.0 in the HDR buffer
+    pub fn set_current_color_hdr(&mut self, rgb: [f32; 3]) {
+        self.current_color = pack(rgb);
+        self.current_hdr = rgb;
+    }
+
+    // Writes a pixel without depth test, e.g. for backgrounds
+    pub fn write_pixel(&mut self, index: usize, color: u32) {
+        self.buffer[index] = color;
+        if let Some(hdr) = &mut self.hdr {
+            hdr[index] = unpack(color);
+        }
+    }
+
+    // The 8-bit buffer alone is the fast default; enabling HDR allocates the
+    // float buffer, seeded from the current image
+    pub fn set_hdr(&mut self, enabled: bool) {
+        self.hdr = if enabled {
+            Some(self.buffer.iter().map(|&pixel| unpack(pixel)).collect())
+        } else {
+            None
+        };
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.hdr.is_some()
+    }
+
+    // Clamps and quantizes the HDR buffer into `buffer`. With `dither`, a
+    // Bayer 4x4 offset below one LSB is added to each channel before
+    // rounding, so smooth gradients break into a fine pattern instead of
+    // bands. Passes that run after this (FXAA, overlays) work on the 8-bit
+    // image; without the HDR buffer there is nothing left to dither.
+    pub fn resolve_to_u32(&mut self, dither: bool) {
+        if let Some(hdr) = &self.hdr {
+            for (index, (pixel, &rgb)) in self.buffer.iter_mut().zip(hdr).enumerate() {
+                *pixel = if dither {
+                    let (x, y) = (index % self.width, index / self.width);
+                    let offset = ((BAYER_4X4[y % 4][x % 4] + 0.5) / 16.0 - 0.5) / 255.0;
+                    pack(rgb.map(|channel| channel + offset))
+                } else {
+                    pack(rgb)
+                };
+            }
+        }
+    }
+
+    // ID written by `point` alongside the color; 0 is reserved for "nothing"
+    pub fn set_current_id(&mut self, id: u32) {
+        self.current_id = id;
+    }
+
+    // Emission written by `point` alongside the color, see select_shader
+    pub fn set_current_emission(&mut self, emission: f32) {
+        self.current_emission = emission;
+    }
+
+    // ID of what was drawn at (x, y) in the last frame, None for empty space
+    pub fn id_at(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        match self.id_buffer[y * self.width + x] {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    // Packed 0xRRGGBB color at (x, y); panics outside the buffer, see `try_get`
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.try_get(x, y).unwrap_or_else(|| {
+            panic!("pixel ({}, {}) outside {}x{} framebuffer", x, y, self.width, self.height)
+        })
+    }
+
+    pub fn try_get(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.buffer[y * self.width + x])
+    }
+
+    // Post-pass applying Color::adjust to every pixel of the 8-bit buffer
+    pub fn apply_adjust(&mut self, brightness: f32, contrast: f32) {
+        if brightness == 1.0 && contrast == 1.0 {
+            return;
+        }
+        for pixel in self.buffer.iter_mut() {
+            *pixel = Color::from_hex(*pixel).adjust(brightness, contrast).to_hex();
+        }
+    }
+
+    // FXAA-style pass: where the luminance contrast around a pixel exceeds
+    // `threshold` (0..1), blend it with the neighbor across the edge. Pixels
+    // brighter or darker than all four neighbors are single-pixel details
+    // (stars, specks) rather than edges, so they are left untouched.
+    pub fn apply_fxaa(&mut self, threshold: f32) {
+        if self.width < 3 || self.height < 3 {
+            return;
+        }
+
+        let source = self.buffer.clone();
+        let luma = |pixel: u32| -> f32 {
+            let r = ((pixel >> 16) & 0xFF) as f32;
+            let g = ((pixel >> 8) & 0xFF) as f32;
+            let b = (pixel & 0xFF) as f32;
+            (0.299 * r + 0.587 * g + 0.114 * b) / 255.0
+        };
+
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let index = y * self.width + x;
+                let neighbors = [
+                    index - self.width, // north
+                    index + self.width, // south
+                    index + 1,          // east
+                    index - 1,          // west
+                ];
+
+                let m = luma(source[index]);
+                let [n, s, e, w] = neighbors.map(|i| luma(source[i]));
+
+                let highest = n.max(s).max(e).max(w);
+                let lowest = n.min(s).min(e).min(w);
+                if highest.max(m) - lowest.min(m) < threshold || m > highest || m < lowest {
+                    continue;
+                }
+
+                // A horizontal edge changes along Y, so blend with N or S
+                let horizontal = (n + s - 2.0 * m).abs() >= (e + w - 2.0 * m).abs();
+                let across = if horizontal {
+                    if (n - m).abs() >= (s - m).abs() { neighbors[0] } else { neighbors[1] }
+                } else if (e - m).abs() >= (w - m).abs() {
+                    neighbors[2]
+                } else {
+                    neighbors[3]
+                };
+
+                let blended = Color::from_hex(source[index]).lerp(&Color::from_hex(source[across]), 0.5);
+                self.buffer[index] = blended.to_hex();
+            }
+        }
+    }
+
+    // Box filter into `target`, for supersampling: each target pixel becomes
+    // the average color of the block of this buffer it covers. With a size
+    // that is not an exact multiple of the target's, the blocks differ by a
+    // pixel; a larger target just repeats pixels.
+    pub fn downsample_into(&self, target: &mut Framebuffer) {
+        let span = |index: usize, source: usize, target: usize| {
+            let start = index * source / target;
+            let end = ((index + 1) * source / target).max(start + 1).min(source);
+            start..end
+        };
+
+        // Reused for every block instead of allocating per target pixel
+        let mut block = Vec::new();
+        for ty in 0..target.height {
+            let rows = span(ty, self.height, target.height);
+            for tx in 0..target.width {
+                let columns = span(tx, self.width, target.width);
+                block.clear();
+                for y in rows.clone() {
+                    let row = &self.buffer[y * self.width + columns.start..y * self.width + columns.end];
+                    block.extend(row.iter().map(|&pixel| Color::from_hex(pixel)));
+                }
+
+                target.write_pixel(ty * target.width + tx, Color::average(&block).to_hex());
+            }
+        }
+    }
+
+    // Replaces the image with the fragment count of each pixel, black for
+    // untouched pixels and blue (one fragment) through red (saturated)
+    pub fn apply_overdraw_heatmap(&mut self) {
+        let palette = Gradient::new(vec![
+            (0.0, Color::new(0, 0, 255)),
+            (0.33, Color::new(0, 255, 255)),
+            (0.5, Color::new(0, 255, 0)),
+            (0.67, Color::new(255, 255, 0)),
+            (1.0, Color::new(255, 0, 0)),
+        ]);
+
+        for (pixel, &count) in self.buffer.iter_mut().zip(&self.overdraw) {
+            *pixel = if count == 0 {
+                0x000000
+            } else {
+                let t = (count - 1) as f32 / (OVERDRAW_SATURATION - 1.0);
+                palette.sample(t).to_hex()
+            };
+        }
     }
-}
\ No newline at end of file
+
+    // Glow around self-lit surfaces. Bright pass: pixels with emission whose
+    // luminance reaches `threshold` (0..1) contribute their color times the
+    // emission; everything else, every non-emissive planet included,
+    // contributes nothing. That is blurred with a separable Gaussian of
+    // `radius` pixels (normalized, so the total light is kept away from the
+    // borders) and added over the image, scaled by `strength`. Only the box
+    // around the bright pixels is blurred. Works on the HDR buffer when
+    // enabled, so it belongs before tone mapping.
+    pub fn apply_bloom(&mut self, threshold: f32, radius: usize, strength: f32) {
+        if radius == 0 || strength <= 0.0 {
+            return;
+        }
+
+        let luma = |rgb: [f32; 3]| 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+        let color_at = |index: usize| match &self.hdr {
+            Some(hdr) => hdr[index],
+            None => unpack(self.buffer[index]),
+        };
+        let is_bright = |index: usize| self.emission_buffer[index] > 0.0 && luma(color_at(index)) >= threshold;
+
+        // Box of the bright pixels, grown by the radius the glow reaches
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if is_bright(y * self.width + x) {
+                    let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x, y));
+                    bounds = Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+                }
+            }
+        }
+        let Some((x0, y0, x1, y1)) = bounds else {
+            return;
+        };
+        let (x0, y0) = (x0.saturating_sub(radius), y0.saturating_sub(radius));
+        let (x1, y1) = ((x1 + radius).min(self.width - 1), (y1 + radius).min(self.height - 1));
+        let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+
+        let mut bright = vec![[0.0; 3]; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let index = (y0 + y) * self.width + x0 + x;
+                if is_bright(index) {
+                    let emission = self.emission_buffer[index];
+                    bright[y * width + x] = color_at(index).map(|channel| channel * emission);
+                }
+            }
+        }
+
+        let glow = gaussian_blur(&bright, width, height, radius);
+
+        for y in 0..height {
+            for x in 0..width {
+                let light = glow[y * width + x];
+                let index = (y0 + y) * self.width + x0 + x;
+                match &mut self.hdr {
+                    Some(hdr) => {
+                        let rgb = hdr[index];
+                        hdr[index] = [0, 1, 2].map(|i| rgb[i] + light[i] * strength);
+                    }
+                    None => {
+                        let rgb = unpack(self.buffer[index]);
+                        self.buffer[index] = pack([0, 1, 2].map(|i| rgb[i] + light[i] * strength));
+                    }
+                }
+            }
+        }
+    }
+
+    // Extended Reinhard tone mapping, per channel. `exposure` scales the
+    // color first; the white point follows it so a full-white pixel stays
+    // white while mid-tones and highlights compress. Works on the HDR buffer
+    // when enabled, otherwise approximately on the packed 8-bit buffer.
+    pub fn apply_tonemap(&mut self, exposure: f32) {
+        if exposure <= 0.0 {
+            return;
+        }
+
+        let white_squared = exposure * exposure;
+        let map = |channel: f32| -> f32 {
+            let v = channel * exposure;
+            v * (1.0 + v / white_squared) / (1.0 + v)
+        };
+
+        match &mut self.hdr {
+            Some(hdr) => {
+                for rgb in hdr.iter_mut() {
+                    *rgb = rgb.map(map);
+                }
+            }
+            None => {
+                for pixel in self.buffer.iter_mut() {
+                    *pixel = pack(unpack(*pixel).map(map));
+                }
+            }
+        }
+    }
+}
+
+// Separable Gaussian blur of a width x height image, sigma = radius / 2.
+// Samples past the edges count as black, so light near a border is lost
+// rather than piled up on it.
+fn gaussian_blur(image: &[[f32; 3]], width: usize, height: usize, radius: usize) -> Vec<[f32; 3]> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let mut weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let offset = i as f32 - radius as f32;
+            (-offset * offset / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.iter_mut().for_each(|weight| *weight /= total);
+
+    // One pass over `count` lines of `length` samples; `at(line, i)` is the
+    // index of the i-th sample of a line
+    let pass = |source: &[[f32; 3]], count: usize, length: usize, at: &dyn Fn(usize, usize) -> usize| {
+        let mut result = vec![[0.0; 3]; source.len()];
+        for line in 0..count {
+            for i in 0..length {
+                let first = i.saturating_sub(radius);
+                let last = (i + radius).min(length - 1);
+                let mut sum = [0.0; 3];
+                for j in first..=last {
+                    let sample = source[at(line, j)];
+                    let weight = weights[j + radius - i];
+                    sum = [sum[0] + sample[0] * weight, sum[1] + sample[1] * weight, sum[2] + sample[2] * weight];
+                }
+                result[at(line, i)] = sum;
+            }
+        }
+        result
+    };
+
+    let rows = pass(image, height, width, &|y, x| y * width + x);
+    pass(&rows, width, height, &|x, y| y * width + x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every pixel covered with the same HDR color at mid depth
+    fn filled(width: usize, height: usize, rgb: [f32; 3]) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_hdr(true);
+        framebuffer.set_current_color_hdr(rgb);
+        for y in 0..height {
+            for x in 0..width {
+                framebuffer.point(x, y, 0.5);
+            }
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn dither_spreads_a_sub_lsb_value_over_the_bayer_tile() {
+        // A quarter of the way from 100 to 101: plain rounding always gives 100
+        let value = 100.25 / 255.0;
+        let mut plain = filled(4, 4, [value; 3]);
+        plain.resolve_to_u32(false);
+        assert!(plain.buffer.iter().all(|&pixel| pixel == 0x646464));
+
+        let mut dithered = filled(4, 4, [value; 3]);
+        dithered.resolve_to_u32(true);
+        let raised = dithered.buffer.iter().filter(|&&pixel| pixel == 0x656565).count();
+        let kept = dithered.buffer.iter().filter(|&&pixel| pixel == 0x646464).count();
+        assert_eq!((raised, kept), (4, 12));
+    }
+
+    #[test]
+    fn dither_leaves_exact_values_alone() {
+        let mut framebuffer = filled(8, 8, [0.0, 128.0 / 255.0, 1.0]);
+        framebuffer.resolve_to_u32(true);
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x0080FF));
+    }
+
+    #[test]
+    fn nearer_depth_wins_in_either_order() {
+        let (near, far) = (0x00FF00, 0xFF0000);
+        for order in [[(far, 0.8), (near, 0.2)], [(near, 0.2), (far, 0.8)]] {
+            let mut framebuffer = Framebuffer::new(4, 4);
+            for (color, depth) in order {
+                framebuffer.set_current_color(color);
+                framebuffer.point(1, 2, depth);
+            }
+            assert_eq!(framebuffer.get(1, 2), near);
+            assert_eq!(framebuffer.zbuffer[2 * 4 + 1], 0.2);
+        }
+    }
+
+    #[test]
+    fn saved_png_round_trips() {
+        let mut framebuffer = Framebuffer::new(6, 4);
+        framebuffer.set_background_color(0x336699);
+        framebuffer.clear();
+        framebuffer.set_current_color(0xFF8000);
+        framebuffer.point(2, 1, 0.5);
+
+        let path = std::env::temp_dir().join(format!("model3d-round-trip-{}.png", std::process::id()));
+        framebuffer.save_png(path.to_str().unwrap()).unwrap();
+        let image = image::open(&path).unwrap().to_rgb8();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(image.dimensions(), (6, 4));
+        assert_eq!(image.get_pixel(0, 0).0, [0x33, 0x66, 0x99]);
+        assert_eq!(image.get_pixel(2, 1).0, [0xFF, 0x80, 0x00]);
+    }
+
+    #[test]
+    fn half_alpha_white_over_black_is_mid_gray() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set_background_color(0x000000);
+        framebuffer.clear();
+        framebuffer.set_current_color(0xFFFFFF);
+        framebuffer.blend(0, 0, 0.5, 0.5);
+
+        let pixel = framebuffer.get(0, 0);
+        assert!(pixel == 0x7F7F7F || pixel == 0x808080, "got {:06X}", pixel);
+        // Depth stays open for what is drawn behind later
+        assert_eq!(framebuffer.zbuffer[0], f32::INFINITY);
+    }
+
+    #[test]
+    fn downsampling_averages_each_block() {
+        let mut source = Framebuffer::new(2, 2);
+        for (index, color) in [0xFF0000, 0x00FF00, 0x0000FF, 0xFFFFFF].into_iter().enumerate() {
+            source.write_pixel(index, color);
+        }
+
+        let mut target = Framebuffer::new(1, 1);
+        source.downsample_into(&mut target);
+        let expected = Color::average(&[0xFF0000, 0x00FF00, 0x0000FF, 0xFFFFFF].map(Color::from_hex));
+        assert_eq!(target.get(0, 0), expected.to_hex());
+        assert_eq!(target.get(0, 0), 0x808080);
+    }
+
+    #[test]
+    fn bloom_spreads_a_hot_pixel_and_keeps_its_energy() {
+        let mut framebuffer = filled(21, 21, [0.0; 3]);
+        framebuffer.set_current_color_hdr([1.0; 3]);
+        framebuffer.set_current_emission(1.0);
+        framebuffer.point(10, 10, 0.1);
+        framebuffer.apply_bloom(0.5, 3, 1.0);
+
+        let red = |x: usize, y: usize| framebuffer.hdr.as_ref().unwrap()[y * 21 + x][0];
+        assert!(red(11, 10) > 0.0 && red(10, 12) > 0.0 && red(12, 12) > 0.0);
+        assert!(red(10, 10) > red(11, 10) && red(11, 10) > red(13, 10));
+
+        // The glow adds back the hot pixel's own light, spread out
+        let total: f32 = framebuffer.hdr.as_ref().unwrap().iter().map(|rgb| rgb[0]).sum();
+        assert!((total - 2.0).abs() < 0.05, "total brightness {}", total);
+    }
+}
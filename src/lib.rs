@@ -0,0 +1,24 @@
+pub mod framebuffer;
+pub mod triangle;
+pub mod vertex;
+pub mod obj;
+pub mod color;
+pub mod fragment;
+pub mod shaders;
+pub mod camera;
+pub mod uniforms;
+pub mod light;
+pub mod render;
+pub mod overlay;
+pub mod recorder;
+pub mod scene;
+pub mod settings;
+pub mod skybox;
+pub mod starfield;
+pub mod timing;
+pub mod lod;
+pub mod cli;
+pub mod crater;
+pub mod clip;
+pub mod texture;
+pub mod line;
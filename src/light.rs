@@ -1,10 +1,14 @@
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{Vec3, rotate_vec3};
+use std::f32::consts::PI;
 use crate::color::Color;
 
 pub struct Light {
     pub position: Vec3,
     pub color: Color,
     pub intensity: f32,
+    // Seconds for one automatic turn around the scene, see `advance`;
+    // 0 keeps the light where it is
+    pub day_length: f32,
 }
 
 impl Light {
@@ -13,6 +17,7 @@ impl Light {
             position,
             color,
             intensity,
+            day_length: 0.0,
         }
     }
 }
@@ -23,6 +28,37 @@ impl Light {
             position: Vec3::new(0.0, 0.0, 0.0), // Posición en el centro del sistema
             color: Color::new(255, 229, 179),   // Color cálido del Sol en formato RGB
             intensity: 1.5,                     // Intensidad alta para simular la luz solar
+            day_length: 0.0,
+        }
+    }
+}
+
+impl Light {
+    // Rotates the light around the scene origin, keeping its distance
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        let radius = self.position.magnitude();
+        if radius == 0.0 {
+            return;
+        }
+
+        let yawed = rotate_vec3(&self.position, delta_yaw, &Vec3::new(0.0, 1.0, 0.0));
+
+        let horizontal = (yawed.x * yawed.x + yawed.z * yawed.z).sqrt();
+        let pitch = yawed.y.atan2(horizontal);
+        let new_pitch = (pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+        let yaw = yawed.z.atan2(yawed.x);
+
+        self.position = Vec3::new(
+            radius * yaw.cos() * new_pitch.cos(),
+            radius * new_pitch.sin(),
+            radius * yaw.sin() * new_pitch.cos(),
+        );
+    }
+
+    // Moves the light along its automatic orbit by `time_step` seconds
+    pub fn advance(&mut self, time_step: f32) {
+        if self.day_length > 0.0 {
+            self.orbit(2.0 * PI * time_step / self.day_length, 0.0);
         }
     }
 }
@@ -1,42 +1,85 @@
-use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
 
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
-
-    let start = a.transformed_position;
-    let end = b.transformed_position;
-
-    let mut x0 = start.x as i32;
-    let mut y0 = start.y as i32;
-    let x1 = end.x as i32;
-    let y1 = end.y as i32;
+// Rasterizes the segment between two transformed vertices and hands each
+// pixel, with its depth interpolated along the segment, to `emit`. The
+// segment is first clipped to the width x height target (Liang-Barsky), so
+// an edge that runs far off screen costs only its visible part; then one
+// pixel is produced per step along the major axis, endpoints included.
+pub fn for_each_line_pixel<F: FnMut(usize, usize, f32)>(
+    a: &Vertex,
+    b: &Vertex,
+    width: usize,
+    height: usize,
+    mut emit: F,
+) {
+    let (start, end) = (a.transformed_position, b.transformed_position);
+    if width == 0 || height == 0 || !(start.x + start.y + end.x + end.y).is_finite() {
+        return;
+    }
 
-    let dx = (x1 - x0).abs();
-    let dy = (y1 - y0).abs();
+    // Clipped in f64: an edge grazing the near plane can reach 1e9 pixels,
+    // where f32 cannot tell neighboring pixels apart
+    let (sx, sy) = (start.x as f64, start.y as f64);
+    let (dx, dy) = (end.x as f64 - sx, end.y as f64 - sy);
+    let (max_x, max_y) = (width as f64 - 1e-3, height as f64 - 1e-3);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    for (p, q) in [(-dx, sx), (dx, max_x - sx), (-dy, sy), (dy, max_y - sy)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return;
+    }
 
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
+    let point = |t: f64| {
+        let x = (sx + dx * t).clamp(0.0, max_x) as f32;
+        let y = (sy + dy * t).clamp(0.0, max_y) as f32;
+        (x, y, start.z + (end.z - start.z) * t as f32)
+    };
+    let (x0, y0, z0) = point(t0);
+    let (x1, y1, z1) = point(t1);
 
-    let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    let steps = (x1.floor() - x0.floor()).abs().max((y1.floor() - y0.floor()).abs()) as usize;
+    for step in 0..=steps {
+        let t = if steps == 0 { 0.0 } else { step as f32 / steps as f32 };
+        let x = (x0 + (x1 - x0) * t).floor() as usize;
+        let y = (y0 + (y1 - y0) * t).floor() as usize;
+        emit(x.min(width - 1), y.min(height - 1), z0 + (z1 - z0) * t);
+    }
+}
 
-    loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
 
-        if x0 == x1 && y0 == y1 { break; }
+    fn pixels(from: (f32, f32), to: (f32, f32)) -> Vec<(usize, usize)> {
+        let vertex = |(x, y): (f32, f32)| Vertex::new(Vec3::new(x, y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+        let mut pixels = Vec::new();
+        for_each_line_pixel(&vertex(from), &vertex(to), 8, 8, |x, y, _| pixels.push((x, y)));
+        pixels
+    }
 
-        let e2 = err;
-        if e2 > -dx {
-            err -= dy;
-            x0 += sx;
-        }
-        if e2 < dy {
-            err += dx;
-            y0 += sy;
-        }
+    #[test]
+    fn horizontal_and_diagonal_lines_light_the_expected_pixels() {
+        assert_eq!(pixels((1.5, 3.5), (6.5, 3.5)), (1..=6).map(|x| (x, 3)).collect::<Vec<_>>());
+        assert_eq!(pixels((0.5, 0.5), (4.5, 4.5)), (0..=4).map(|i| (i, i)).collect::<Vec<_>>());
     }
 
-    fragments
-}
\ No newline at end of file
+    #[test]
+    fn lines_are_clipped_to_the_target() {
+        let clipped = pixels((-100.5, 2.5), (100.5, 2.5));
+        assert_eq!(clipped, (0..8).map(|x| (x, 2)).collect::<Vec<_>>());
+        assert!(pixels((-5.0, -5.0), (-1.0, -9.0)).is_empty());
+    }
+}
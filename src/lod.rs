@@ -0,0 +1,118 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::obj::Obj;
+use crate::vertex::Vertex;
+
+// Latitude segments of each generated level, coarsest first (longitudes
+// are twice as many)
+const LOD_SEGMENTS: [u32; 2] = [8, 16];
+
+// On-screen radius in pixels where each level hands over to the next finer
+// one; above the last threshold the body's own mesh is drawn
+const LOD_THRESHOLDS: [f32; 2] = [12.0, 40.0];
+
+// A level only changes once the radius is this fraction past a threshold,
+// so a body hovering at the boundary does not flicker between meshes
+const LOD_HYSTERESIS: f32 = 0.2;
+
+// Coarse sphere meshes swapped in for bodies that appear small on screen
+pub struct Lod {
+    // Radius of the generated spheres, the same as the meshes they replace
+    radius: f32,
+    spheres: Vec<Vec<Vertex>>,
+    // Current level per body, LOD_SEGMENTS.len() meaning the full mesh
+    levels: Vec<usize>,
+}
+
+impl Lod {
+    pub fn new(radius: f32, body_count: usize) -> Self {
+        let spheres = LOD_SEGMENTS.iter()
+            .map(|&segments| Obj::uv_sphere(segments, segments * 2, radius).get_vertex_array())
+            .collect();
+
+        Lod {
+            radius,
+            spheres,
+            levels: vec![LOD_SEGMENTS.len(); body_count],
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    // Vertex array to draw `body` with, given its on-screen radius in pixels
+    pub fn select<'a>(&'a mut self, body: usize, pixel_radius: f32, full: &'a [Vertex]) -> &'a [Vertex] {
+        let level = match self.levels.get_mut(body) {
+            Some(level) => {
+                *level = next_level(*level, pixel_radius);
+                *level
+            }
+            None => return full,
+        };
+
+        match self.spheres.get(level) {
+            Some(sphere) => sphere,
+            None => full,
+        }
+    }
+}
+
+fn next_level(current: usize, pixel_radius: f32) -> usize {
+    let mut level = current.min(LOD_THRESHOLDS.len());
+
+    while level < LOD_THRESHOLDS.len() && pixel_radius >= LOD_THRESHOLDS[level] * (1.0 + LOD_HYSTERESIS) {
+        level += 1;
+    }
+    while level > 0 && pixel_radius < LOD_THRESHOLDS[level - 1] * (1.0 - LOD_HYSTERESIS) {
+        level -= 1;
+    }
+
+    level
+}
+
+// Approximate radius in pixels of a sphere of `radius` at world `center`.
+// projection[(1, 1)] is 1 / tan(fov_y / 2) for a perspective matrix.
+pub fn screen_radius(center: Vec3, radius: f32, view_matrix: &Mat4, projection_matrix: &Mat4, viewport_height: f32) -> f32 {
+    let view_position = view_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+    let distance = view_position.xyz().magnitude();
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+
+    radius * projection_matrix[(1, 1)] / distance * viewport_height / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_level_waits_past_the_threshold() {
+        let threshold = LOD_THRESHOLDS[0];
+        // Just past the threshold is not enough to refine...
+        assert_eq!(next_level(0, threshold * 1.1), 0);
+        assert_eq!(next_level(0, threshold * (1.0 + LOD_HYSTERESIS)), 1);
+        // ...nor just below it to coarsen again
+        assert_eq!(next_level(1, threshold * 0.9), 1);
+        assert_eq!(next_level(1, threshold * (1.0 - LOD_HYSTERESIS) * 0.99), 0);
+    }
+
+    #[test]
+    fn next_level_jumps_several_levels_at_once() {
+        assert_eq!(next_level(0, 1000.0), LOD_THRESHOLDS.len());
+        assert_eq!(next_level(LOD_THRESHOLDS.len(), 1.0), 0);
+        // Out-of-range levels are treated as the full mesh
+        assert_eq!(next_level(99, 1000.0), LOD_THRESHOLDS.len());
+    }
+
+    #[test]
+    fn select_falls_back_to_the_full_mesh() {
+        let full = Obj::uv_sphere(32, 64, 1.0).get_vertex_array();
+        let mut lod = Lod::new(1.0, 1);
+        assert_eq!(lod.select(0, 1000.0, &full).len(), full.len());
+        assert!(lod.select(0, 1.0, &full).len() < full.len());
+        // Unknown bodies are never simplified
+        assert_eq!(lod.select(5, 1.0, &full).len(), full.len());
+    }
+}
@@ -1,78 +1,130 @@
-use nalgebra_glm::{Vec3, Mat4};
-use minifb::{Key, Window, WindowOptions};
-use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
+use nalgebra_glm::{Vec3, rotate_vec3};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-mod framebuffer;
-mod triangle;
-mod vertex;
-mod obj;
-mod color;
-mod fragment;
-mod shaders;
-mod camera;
-mod uniforms;
-mod light;
+// Radio al que se normalizan los modelos cargados; la esfera generada usa el mismo
+const SPHERE_RADIUS: f32 = 3.5;
 
-use framebuffer::Framebuffer;
-use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, select_shader};
-use uniforms::{Uniforms, create_noise, create_model_matrix, create_view_matrix, create_perspective_matrix, create_viewport_matrix};
+// Contraste mínimo de luminancia para suavizar un borde
+const FXAA_THRESHOLD: f32 = 0.1;
+// Resplandor: luminancia mínima de lo que brilla, radio en píxeles de la
+// ventana (se multiplica por el supermuestreo) e intensidad al sumarlo
+const BLOOM_THRESHOLD: f32 = 0.5;
+const BLOOM_RADIUS: usize = 16;
+const BLOOM_STRENGTH: f32 = 1.0;
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
+// Escena editable; si falta o es inválida se usa la escena integrada
+const SCENE_PATH: &str = "assets/scene.toml";
 
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
-        }
-    }
+// Carpeta y cantidad máxima de cuadros al grabar
+const RECORD_DIRECTORY: &str = "recordings";
+const RECORD_MAX_FRAMES: usize = 600;
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
+// Límite de cuadros por defecto de minifb (4 ms) y ventana de cuadros para
+// las estadísticas del modo sin límite
+const TARGET_FPS: usize = 250;
+const FRAME_STATS_WINDOW: usize = 120;
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = select_shader(index, &fragment, &uniforms);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
-    }
-}
+// Distancia de la cámara al entrar en la vista del sistema completo
+const SYSTEM_VIEW_DISTANCE: f32 = 160.0;
+
+// Duraciones del día en segundos que recorren Shift+J (más corto) y
+// Shift+L (más largo); 0 deja la luz quieta
+const DAY_LENGTHS: [f32; 6] = [0.0, 32.0, 16.0, 8.0, 4.0, 2.0];
+
+// A partir de este tamaño se informa el avance al cargar un modelo
+const LARGE_MODEL_BYTES: u64 = 32 * 1024 * 1024;
+
+// Canales en el orden en que Delete los recorre y paso de ; y '
+const CHANNEL_NAMES: [&str; 3] = ["R", "G", "B"];
+const PALETTE_STEP: i32 = 5;
+
+// Velocidades del giro de exhibición (radianes por segundo) que recorre Enter
+const TURNTABLE_SPEEDS: [f32; 4] = [0.3, 0.6, 1.2, 2.4];
+
+// Segundos del recorrido de cámara entre un marcador y el siguiente
+const TOUR_SECONDS_PER_KEYFRAME: f32 = 1.5;
+
+// Paso de desplazamiento y de giro del plano de corte
+const CLIP_OFFSET_STEP: f32 = 0.25;
+const CLIP_ANGLE_STEP: f32 = PI / 36.0;
+
+// Mouse: radianes de giro por píxel arrastrado, factor de distancia por
+// paso de la rueda al orbitar y avance por paso en vuelo libre
+const MOUSE_SENSITIVITY: f32 = 0.005;
+const SCROLL_ZOOM_FACTOR: f32 = 0.9;
+const SCROLL_FLY_STEP: f32 = 2.0;
+
+// Carpeta opcional con las seis caras del fondo (px, nx, py, ny, pz, nz .png)
+const SKYBOX_DIRECTORY: &str = "assets/skybox";
+
+// Carpeta opcional con mapas de albedo (<planeta>.png) que se mezclan con las
+// superficies procedurales de los planetas que los usan
+const TEXTURE_DIRECTORY: &str = "assets/textures";
+const TEXTURE_NAMES: [&str; 2] = ["earth", "mars"];
+
+use model3d::cli::{Args, USAGE};
+use model3d::framebuffer::Framebuffer;
+use model3d::obj::{Obj, MIN_SPHERE_SEGMENTS, MAX_SPHERE_SEGMENTS};
+use model3d::color::Color;
+use model3d::crater::{DEFAULT_CRATER_SEED, DEFAULT_CRATER_COUNT};
+use model3d::camera::{Camera, CameraMode, CameraState, Bookmarks, MouseDrag, MouseGesture, OrbitTarget, Tour, drag_angles};
+use model3d::light::Light;
+use model3d::lod::Lod;
+use model3d::render::{render_body, render_rings, render_system, render_system_rings, RenderStats};
+use model3d::shaders::{SHADER_PARAMS, ColorParam, param, palette, color_param, set_color_param};
+use model3d::overlay::{draw_axis_gizmo, draw_panel};
+use model3d::recorder::Recorder;
+use model3d::scene::{Body, Scene};
+use model3d::skybox::Skybox;
+use model3d::starfield::{Starfield, DEFAULT_STAR_SEED, DEFAULT_STAR_DENSITY};
+use model3d::texture::Texture;
+use model3d::timing::{FrameClock, FrameStats};
+use model3d::settings::{RenderSettings, PhotoMode, Turntable, ViewMode, SUPERSAMPLE_FACTORS};
+use model3d::vertex::Vertex;
+use model3d::uniforms::{Uniforms, ClipPlane, WireframeMode, cycle_noise_type, create_view_matrix, create_perspective_matrix, create_viewport_matrix};
 
 fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let window_width = args.width();
+    let window_height = args.height();
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    // El framebuffer sigue el tamaño de la ventana, ver resize_to_window.
+    // minifb no tiene pantalla completa: --fullscreen abre una ventana sin
+    // bordes, siempre encima y en la esquina, del tamaño pedido
+    let mut framebuffer = Framebuffer::new(window_width, window_height);
+    // Imagen del tamaño de la ventana: la escena reducida desde `framebuffer`
+    // (más grande con supermuestreo) más los elementos superpuestos
+    let mut display = Framebuffer::new(window_width, window_height);
     let mut window = Window::new(
         "Render Planet",
         window_width,
         window_height,
-        WindowOptions::default(),
+        WindowOptions {
+            resize: !args.fullscreen,
+            borderless: args.fullscreen,
+            title: !args.fullscreen,
+            topmost: args.fullscreen,
+            ..WindowOptions::default()
+        },
     )
     .unwrap();
 
-    window.set_position(500, 500);
+    if args.fullscreen {
+        window.set_position(0, 0);
+    } else {
+        window.set_position(500, 500);
+    }
+    window.set_target_fps(TARGET_FPS);
     window.update();
 
     framebuffer.set_background_color(0x333355);
@@ -83,72 +135,481 @@ fn main() {
         Vec3::new(0.0, 1.0, 0.0),
     );
 
-    let obj = Obj::load("assets/model/sphere.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array();
-    let noise = create_noise();
-    let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-
-    let mut uniforms = Uniforms {
-        model_matrix: Mat4::identity(),
-        view_matrix: Mat4::identity(),
-        projection_matrix,
-        viewport_matrix,
-        time: 0,
-        noise,
-    };
+    let (scene, meshes) = load_scene(SCENE_PATH, args.model.as_deref(), args.flip_winding);
+    let skybox = load_skybox(SKYBOX_DIRECTORY);
+    let starfield = Starfield::new(DEFAULT_STAR_SEED, DEFAULT_STAR_DENSITY);
+
+    // Esfera generada que reemplaza a los modelos cargados, ver sphere_segments_input
+    let mut sphere_segments: Option<u32> = None;
+    let mut generated_sphere: Option<Vec<Vertex>> = None;
+    let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+    let mut uniforms = Uniforms::new(projection_matrix, viewport_matrix);
+    if args.crater_seed.is_some() || args.crater_count.is_some() {
+        uniforms.set_craters(
+            args.crater_seed.unwrap_or(DEFAULT_CRATER_SEED),
+            args.crater_count.unwrap_or(DEFAULT_CRATER_COUNT),
+        );
+    }
+    uniforms.textures = load_textures(TEXTURE_DIRECTORY);
+
+    // Esferas más simples para los cuerpos que se ven pequeños en la vista del sistema
+    let mut lod = Lod::new(SPHERE_RADIUS, scene.bodies.len());
+
+    let mut bookmarks = Bookmarks::new();
+    // Recorrido en curso por los marcadores guardados, ver handle_tour_input
+    let mut tour: Option<Tour> = None;
 
     let mut selected_planet = 0; // Inicialmente, el sol
+    let mut orbit_target = OrbitTarget::System;
+    let mut camera_mode = CameraMode::Orbit;
+    let mut view_mode = ViewMode::Single;
+    // Cámara de la otra vista, para volver a ella al alternar con Tab
+    let mut other_view_camera: Option<CameraState> = None;
+    let mut settings = RenderSettings::default();
+    let mut photo_mode = PhotoMode::new();
+    let mut turntable = Turntable::new(TURNTABLE_SPEEDS[1]);
+    let mut recorder = Recorder::new(RECORD_DIRECTORY, RECORD_MAX_FRAMES);
+    let mut uncapped = false;
+    let mut frame_stats = FrameStats::new(FRAME_STATS_WINDOW);
+    let mut frame_clock = FrameClock::default();
+    let mut title = String::new();
+    let mut mouse_drag = MouseDrag::default();
+    let mut selected_param = 0;
+    let mut selected_color = 0;
+    let mut selected_channel = 0;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
-        handle_input(&window, &mut camera);
+        // Ventana minimizada: no hay dónde dibujar, solo se atienden los eventos
+        if !resize_to_window(&window, &mut framebuffer, &mut display, &mut uniforms, settings.supersample) {
+            window.update();
+            continue;
+        }
+
+        // Segundos desde el cuadro anterior; todo lo animado avanza con esto
+        let dt = frame_clock.tick();
+
+        // Cualquier tecla de órbita devuelve el control de la cámara
+        if handle_input(&window, &mut camera, &mut camera_mode) {
+            turntable.active = false;
+        }
+        handle_turntable_input(&window, &mut turntable);
+        let body = &scene.bodies[selected_planet];
+        let vertex_array = generated_sphere.as_ref().unwrap_or(&meshes[&body.mesh]);
+        handle_debug_input(&window, &mut uniforms, vertex_array.len() / 3);
+        handle_bookmark_input(&window, &mut bookmarks, &camera);
+        bookmarks.update(&mut camera, dt);
+        handle_tour_input(&window, &mut tour, &bookmarks);
+        if let Some(active) = tour.as_mut() {
+            if !active.update(&mut camera, dt) {
+                println!("Recorrido terminado");
+                tour = None;
+            }
+        }
+        handle_light_input(&window, &mut uniforms.light);
+        handle_clip_input(&window, &mut uniforms.clip_plane);
+        handle_param_input(&window, &mut uniforms, &mut selected_param);
+        let colors = palette(body.shader);
+        handle_palette_input(&window, &mut uniforms, colors, &mut selected_color, &mut selected_channel);
+
+        // Calidad del muestreo de ruido: 1, 4 u 8 muestras por fragmento
+        if window.is_key_pressed(Key::U, KeyRepeat::No) {
+            uniforms.noise_samples = match uniforms.noise_samples {
+                1 => 4,
+                4 => 8,
+                _ => 1,
+            };
+        }
+
+        // F8 cambia el tipo de ruido del planeta elegido conservando la
+        // semilla y la frecuencia
+        if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+            if let Some(noise) = uniforms.planet_noise.get_mut(scene.bodies[selected_planet].shader) {
+                cycle_noise_type(noise);
+            }
+        }
+
+        // = y - cambian la densidad de la esfera generada y reconstruyen la malla
+        if let Some(segments) = sphere_segments_input(&window, sphere_segments) {
+            sphere_segments = Some(segments);
+            generated_sphere = Some(Obj::uv_sphere(segments, segments * 2, SPHERE_RADIUS).get_vertex_array());
+            uniforms.solo_triangle = None;
+        }
+
+        // Arrastrar orbita y también devuelve el control de la cámara; un
+        // clic sin arrastrar identifica el planeta bajo el cursor con el
+        // cuadro anterior
+        match handle_mouse_input(&window, &mut camera, camera_mode, &mut mouse_drag) {
+            MouseGesture::Drag(dx, dy) if dx != 0.0 || dy != 0.0 => turntable.active = false,
+            MouseGesture::Click => match pick_body(&window, &framebuffer) {
+                Some(index) if index < scene.bodies.len() => {
+                    println!("Planeta: {}", scene.bodies[index].name);
+                    selected_planet = index;
+                }
+                _ => println!("Planeta: ninguno"),
+            },
+            _ => {}
+        }
 
         // Cambiar el planeta seleccionado según la tecla presionada
         selected_planet = match get_planet_key(&window) {
-            Some(index) => index,
-            None => selected_planet,
+            Some(index) if index < scene.bodies.len() => index,
+            _ => selected_planet,
         };
+        let body = &scene.bodies[selected_planet];
 
+        // Solo se mide el trabajo de render y posprocesado, sin la espera del
+        // límite de cuadros, los elementos superpuestos ni el guardado de PNG
+        frame_stats.begin();
+        // El tramado necesita los valores sin cuantizar, así que también
+        // enciende el búfer en punto flotante
+        let float_buffer = settings.hdr || settings.dither;
+        if framebuffer.is_hdr() != float_buffer {
+            framebuffer.set_hdr(float_buffer);
+        }
+        uniforms.hdr = settings.hdr;
         framebuffer.clear();
 
-        // Configurar la matriz de modelo para el planeta seleccionado
-        let translation = Vec3::new(0.0, 0.0, 0.0);
-        let rotation = Vec3::new(0.0, 0.0, 0.0);
-        let scale = if selected_planet == 0 { 1.5 } else { 1.0 }; // Escala mayor para el sol
+        // Tab alterna entre un solo planeta y el sistema completo; el planeta
+        // seleccionado se conserva y cada vista recuerda su cámara
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            let current = camera.state();
+            match other_view_camera.replace(current) {
+                Some(state) => camera.set_state(&state),
+                None => camera.set_distance(SYSTEM_VIEW_DISTANCE),
+            }
+            view_mode = match view_mode {
+                ViewMode::Single => ViewMode::System,
+                ViewMode::System => ViewMode::Single,
+            };
+        }
+
+        // Posición del planeta seleccionado en la vista actual
+        let selected_translation = match view_mode {
+            ViewMode::Single => Vec3::new(0.0, 0.0, 0.0),
+            ViewMode::System => turntable.rotate(Vec3::from(scene.orbit_position(selected_planet, uniforms.orbit_time))),
+        };
+
+        // F alterna entre orbitar el origen del sistema o seguir al planeta
+        let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        if !shift && window.is_key_pressed(Key::F, KeyRepeat::No) {
+            orbit_target = match orbit_target {
+                OrbitTarget::System => OrbitTarget::Planet,
+                OrbitTarget::Planet => {
+                    camera.follow(Vec3::new(0.0, 0.0, 0.0));
+                    OrbitTarget::System
+                }
+            };
+        }
+        // En vuelo libre la cámara no se deja arrastrar por el planeta
+        if orbit_target == OrbitTarget::Planet && camera_mode == CameraMode::Orbit {
+            camera.follow(selected_translation);
+        }
 
-        uniforms.model_matrix = create_model_matrix(translation, scale, rotation);
         uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        uniforms.time += 1;
+        uniforms.advance_time(dt);
+        turntable.advance(dt);
+        let mut render_stats = RenderStats::default();
+        // El sol queda en el origen, que el giro de exhibición no mueve; los
+        // satélites orbitan la posición de su planeta
+        let positions: Vec<Vec3> = (0..scene.bodies.len())
+            .map(|index| turntable.rotate(Vec3::from(scene.orbit_position(index, uniforms.orbit_time))))
+            .collect();
+
+        match view_mode {
+            ViewMode::Single => {
+                uniforms.occluders.clear();
+                let vertex_array = generated_sphere.as_ref().unwrap_or(&meshes[&body.mesh]);
+                render_stats += render_body(&mut framebuffer, &mut uniforms, body, selected_planet, Vec3::new(0.0, 0.0, 0.0), &turntable.matrix(), vertex_array);
+            }
+            ViewMode::System => {
+                let full_mesh = |body: &Body| generated_sphere.as_deref().unwrap_or(meshes[&body.mesh].as_slice());
+                render_stats += render_system(&mut framebuffer, &mut uniforms, &scene.bodies, &positions, &turntable.matrix(), &mut lod, full_mesh);
+            }
+        }
+
+        // Sin skybox se dibujan estrellas sobre el color de fondo; Shift+F12
+        // las quita y deja el fondo plano
+        if shift && window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            settings.stars = !settings.stars;
+        }
+        if let Some(skybox) = &skybox {
+            skybox.draw(&mut framebuffer, &uniforms.view_matrix, &uniforms.projection_matrix);
+        } else if settings.stars {
+            starfield.draw(&mut framebuffer, &uniforms.view_matrix, &uniforms.projection_matrix, settings.supersample);
+        }
 
-        render(&mut framebuffer, &uniforms, &vertex_arrays, selected_planet);
+        // Los anillos son translúcidos: van después de todo lo opaco y del
+        // fondo para mezclarse con lo que queda detrás
+        match view_mode {
+            ViewMode::Single => {
+                uniforms.occluders.clear();
+                render_stats += render_rings(&mut framebuffer, &mut uniforms, body, Vec3::new(0.0, 0.0, 0.0), &turntable.matrix(), SPHERE_RADIUS);
+            }
+            ViewMode::System => {
+                render_stats += render_system_rings(&mut framebuffer, &mut uniforms, &scene.bodies, &positions, &turntable.matrix(), SPHERE_RADIUS);
+            }
+        }
+
+        // Shift+F11 activa el resplandor de lo que emite luz propia (el sol);
+        // va antes del mapeo de tonos para aprovechar el búfer HDR
+        if shift && window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            settings.bloom = !settings.bloom;
+        }
+        if settings.bloom {
+            framebuffer.apply_bloom(BLOOM_THRESHOLD, BLOOM_RADIUS * settings.supersample, BLOOM_STRENGTH);
+        }
+
+        // F10 activa el modo foto: máxima calidad y sin elementos de depuración
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            photo_mode.toggle(&mut settings, &mut uniforms);
+            println!("Modo foto {}", if photo_mode.is_active() { "activado" } else { "desactivado" });
+        }
+
+        // F11 activa el mapeo de tonos; , y . bajan y suben la exposición
+        if !shift && window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            settings.tonemap = !settings.tonemap;
+        }
+        if !shift && window.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+            settings.exposure = (settings.exposure - 0.1).max(0.1);
+        }
+        if !shift && window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+            settings.exposure = (settings.exposure + 0.1).min(8.0);
+        }
+        if settings.tonemap {
+            framebuffer.apply_tonemap(settings.exposure);
+        }
+
+        // F12 usa el búfer de color en punto flotante hasta este punto; las
+        // pasadas siguientes trabajan sobre la imagen de 8 bits
+        if !shift && window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            settings.hdr = !settings.hdr;
+        }
+        // H activa el tramado para reducir el bandeado de color; se aplica
+        // al cuantizar a 8 bits
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            settings.dither = !settings.dither;
+        }
+        framebuffer.resolve_to_u32(settings.dither);
+
+        // Shift+, y Shift+. cambian el brillo, Shift+- y Shift+= el contraste
+        if shift {
+            if window.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+                settings.brightness = (settings.brightness - 0.05).max(0.0);
+            }
+            if window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+                settings.brightness = (settings.brightness + 0.05).min(2.0);
+            }
+            if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+                settings.contrast = (settings.contrast - 0.05).max(0.0);
+            }
+            if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+                settings.contrast = (settings.contrast + 0.05).min(4.0);
+            }
+        }
+        framebuffer.apply_adjust(settings.brightness, settings.contrast);
+
+        // F3 muestra cuántos fragmentos llegaron a cada píxel (azul a rojo)
+        if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+            settings.overdraw = !settings.overdraw;
+        }
+        if settings.overdraw {
+            framebuffer.apply_overdraw_heatmap();
+        }
+
+        // Shift+F2 cambia el supermuestreo (1x, 2x, 4x); desde aquí se trabaja
+        // con la imagen del tamaño de la ventana
+        if shift && window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            let current = SUPERSAMPLE_FACTORS.iter().position(|&factor| factor == settings.supersample).unwrap_or(0);
+            settings.supersample = SUPERSAMPLE_FACTORS[(current + 1) % SUPERSAMPLE_FACTORS.len()];
+        }
+        framebuffer.downsample_into(&mut display);
+
+        // F2 activa el antialiasing por detección de bordes
+        if !shift && window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            settings.fxaa = !settings.fxaa;
+        }
+        if settings.fxaa {
+            display.apply_fxaa(FXAA_THRESHOLD);
+        }
+
+        frame_stats.end();
+
+        // F4 quita el límite de cuadros e informa tiempos por consola
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            uncapped = !uncapped;
+            window.set_target_fps(if uncapped { 0 } else { TARGET_FPS });
+            frame_stats.clear();
+            println!("Límite de cuadros {}", if uncapped { "desactivado" } else { "activado" });
+        }
+        if uncapped && frame_stats.window_complete() {
+            println!(
+                "Cuadro: min {:.2} ms, prom {:.2} ms, max {:.2} ms",
+                frame_stats.min().as_secs_f64() * 1000.0,
+                frame_stats.average().as_secs_f64() * 1000.0,
+                frame_stats.max().as_secs_f64() * 1000.0,
+            );
+            println!(
+                "Último cuadro: {} vértices, {} triángulos ({} omitidos, {} recortados, {} descartados), {} fragmentos ({} sombreados)",
+                render_stats.vertices,
+                render_stats.triangles,
+                render_stats.triangles_skipped,
+                render_stats.triangles_clipped,
+                render_stats.triangles_culled,
+                render_stats.fragments,
+                render_stats.fragments_shaded,
+            );
+            frame_stats.clear();
+        }
+
+        // Y muestra los ejes de orientación y la mira
+        if window.is_key_pressed(Key::Y, KeyRepeat::No) {
+            settings.show_gizmo = !settings.show_gizmo;
+        }
+        if settings.show_gizmo {
+            draw_axis_gizmo(&mut display, &uniforms.view_matrix);
+        }
+
+        // ` muestra el panel con el estado de cada opción de depuración
+        if window.is_key_pressed(Key::Backquote, KeyRepeat::No) {
+            settings.show_panel = !settings.show_panel;
+        }
+        if settings.show_panel {
+            let on_off = |enabled: bool| if enabled { "SI" } else { "NO" };
+            let lines = [
+                format!("FXAA: {}", on_off(settings.fxaa)),
+                format!("SUPERMUESTREO: {}X", settings.supersample),
+                format!("TRAMADO: {}", on_off(settings.dither)),
+                format!("TONOS: {} EXP {:.1}", on_off(settings.tonemap), settings.exposure),
+                format!("RESPLANDOR: {}", on_off(settings.bloom)),
+                format!("ESTRELLAS: {}", on_off(settings.stars)),
+                format!("BRILLO: {:.2} CONTRASTE {:.2}", settings.brightness, settings.contrast),
+                format!("HDR: {}", on_off(settings.hdr)),
+                format!("EJES: {}", on_off(settings.show_gizmo)),
+                format!("SOBREDIBUJO: {}", on_off(settings.overdraw)),
+                format!("FOTO: {}", on_off(photo_mode.is_active())),
+                format!("VISTA: {}", if view_mode == ViewMode::Single { "PLANETA" } else { "SISTEMA" }),
+                format!("SEGUIR: {}", on_off(orbit_target == OrbitTarget::Planet)),
+                format!("VUELO LIBRE: {}", on_off(camera_mode == CameraMode::Fly)),
+                format!("RECORRIDO: {}", match &tour {
+                    Some(active) if active.paused => "PAUSA",
+                    Some(_) => "SI",
+                    None => "NO",
+                }),
+                format!("GIRO: {}", on_off(!uniforms.spin_paused)),
+                format!("EXHIBICION: {} VEL {:.1}", on_off(turntable.active), turntable.speed),
+                format!("ALAMBRE: {}", match uniforms.wireframe {
+                    WireframeMode::Off => "NO",
+                    WireframeMode::Hidden => "VISIBLES",
+                    WireframeMode::All => "TODAS",
+                }),
+                format!("CARAS TRASERAS: {}", on_off(uniforms.tint_backfaces)),
+                format!("DESCARTE: {}", on_off(uniforms.cull_backfaces)),
+                format!("COLOR DE VERTICE: {}", on_off(uniforms.vertex_colors)),
+                format!("SOLO TRIANGULO: {}", on_off(uniforms.solo_triangle.is_some())),
+                match &uniforms.clip_plane {
+                    Some(plane) => format!("CORTE: SI {:.2}", plane.offset),
+                    None => "CORTE: NO".to_string(),
+                },
+                format!("RUIDO: {} MUESTRAS", uniforms.noise_samples),
+                format!("GRABANDO: {}", on_off(recorder.is_active())),
+                format!("SIN LIMITE: {}", on_off(uncapped)),
+                format!("VERTICES: {}", render_stats.vertices),
+                format!("TRIANGULOS: {} OMITIDOS {} RECORTADOS {} DESCARTADOS {}", render_stats.triangles, render_stats.triangles_skipped, render_stats.triangles_clipped, render_stats.triangles_culled),
+                format!("FRAGMENTOS: {} SOMBREADOS {}", render_stats.fragments, render_stats.fragments_shaded),
+                format!("LUZ: {:.1}", uniforms.light.intensity),
+                // Dirección desde el origen hacia la luz que mueven J/L e I/O
+                match uniforms.light.position.try_normalize(f32::EPSILON) {
+                    Some(direction) => format!("DIRECCION LUZ: {:.2} {:.2} {:.2}", direction.x, direction.y, direction.z),
+                    None => "DIRECCION LUZ: ORIGEN".to_string(),
+                },
+                if uniforms.light.day_length > 0.0 {
+                    format!("DIA: {:.0} S", uniforms.light.day_length)
+                } else {
+                    "DIA: PARADO".to_string()
+                },
+                palette_line(&uniforms, &colors[selected_color % colors.len()], selected_channel),
+            ];
+            draw_panel(&mut display, &lines);
+        }
+
+        // F9 inicia o detiene la grabación de cuadros
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            recorder.toggle();
+            println!("Grabación {}", if recorder.is_active() { "iniciada" } else { "detenida" });
+        }
+        if let Err(error) = recorder.capture(&display) {
+            eprintln!("No se pudo guardar el cuadro: {}", error);
+            recorder.toggle();
+        }
+
+        // P guarda una captura del cuadro tal como se ve en pantalla
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            let path = screenshot_path();
+            match display.save_png(&path) {
+                Ok(()) => println!("Captura guardada en {}", path),
+                Err(error) => eprintln!("No se pudo guardar la captura: {}", error),
+            }
+        }
+
+        // Información en la barra de título, solo se actualiza si cambia
+        let new_title = format!(
+            "Render Planet | distancia {:.1} | ruido {:?}",
+            camera.distance(),
+            uniforms.noise_for(scene.bodies[selected_planet].shader).noise_type,
+        );
+        if new_title != title {
+            window.set_title(&new_title);
+            title = new_title;
+        }
 
         window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+            .update_with_buffer(&display.buffer, display.width, display.height)
             .unwrap();
     }
 }
 
-fn handle_input(window: &Window, camera: &mut Camera) {
+// Returns true when an orbit key was held this frame
+fn handle_input(window: &Window, camera: &mut Camera, mode: &mut CameraMode) -> bool {
     let movement_speed = 1.0;
     let rotation_speed = PI / 50.0;
     let zoom_speed = 1.0;
 
+    // Shift+F alterna entre orbitar y el vuelo libre
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    if shift && window.is_key_pressed(Key::F, KeyRepeat::No) {
+        *mode = match mode {
+            CameraMode::Orbit => CameraMode::Fly,
+            CameraMode::Fly => CameraMode::Orbit,
+        };
+    }
+    if *mode == CameraMode::Fly {
+        return handle_fly_input(window, camera, movement_speed, rotation_speed);
+    }
+
+    let mut orbited = false;
+
     //  camera orbit controls
     if window.is_key_down(Key::Left) {
         camera.orbit(rotation_speed, 0.0);
+        orbited = true;
     }
     if window.is_key_down(Key::Right) {
         camera.orbit(-rotation_speed, 0.0);
+        orbited = true;
     }
     if window.is_key_down(Key::W) {
         camera.orbit(0.0, -rotation_speed);
+        orbited = true;
     }
     if window.is_key_down(Key::S) {
         camera.orbit(0.0, rotation_speed);
+        orbited = true;
     }
 
     // Camera movement controls
@@ -176,27 +637,597 @@ fn handle_input(window: &Window, camera: &mut Camera) {
     if window.is_key_down(Key::Down) {
         camera.zoom(-zoom_speed);
     }
+
+    orbited
 }
 
-// Función para obtener el índice del planeta según la tecla presionada
-fn get_planet_key(window: &Window) -> Option<usize> {
-    if window.is_key_down(Key::Z) {
-        Some(0) // Sol
-    } else if window.is_key_down(Key::X) {
-        Some(1) // Mercurio
-    } else if window.is_key_down(Key::C) {
-        Some(3) // Venus
-    } else if window.is_key_down(Key::V) {
-        Some(2) // Tierra
-    } else if window.is_key_down(Key::B) {
-        Some(4) // Marte
-    } else if window.is_key_down(Key::N) {
-        Some(5) // Júpiter
-    } else if window.is_key_down(Key::M) {
-        Some(6) // Saturno
-    } else if window.is_key_down(Key::K) {
-        Some(7) // Urano
+// Arrastrar con el botón izquierdo orbita alrededor del centro, o gira la
+// vista en vuelo libre, y la rueda acerca o aleja; el teclado sigue
+// funcionando a la par. Devuelve el gesto del botón en este cuadro.
+fn handle_mouse_input(window: &Window, camera: &mut Camera, mode: CameraMode, drag: &mut MouseDrag) -> MouseGesture {
+    let down = window.get_mouse_down(MouseButton::Left);
+    let gesture = drag.update(down, window.get_mouse_pos(MouseMode::Pass));
+    if let MouseGesture::Drag(dx, dy) = gesture {
+        let (yaw, pitch) = drag_angles(dx, dy, MOUSE_SENSITIVITY);
+        if yaw != 0.0 || pitch != 0.0 {
+            match mode {
+                CameraMode::Orbit => camera.orbit(yaw, pitch),
+                CameraMode::Fly => camera.look(yaw, pitch),
+            }
+        }
+    }
+
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        if scroll != 0.0 {
+            match mode {
+                CameraMode::Orbit => camera.set_distance(camera.distance() * SCROLL_ZOOM_FACTOR.powf(scroll)),
+                CameraMode::Fly => camera.move_forward(scroll * SCROLL_FLY_STEP),
+            }
+        }
+    }
+
+    gesture
+}
+
+// Vuelo libre: WASD avanza y se desliza hacia donde se mira, Q/E sube y
+// baja, y las flechas giran la vista. Devuelve si la cámara se movió.
+fn handle_fly_input(window: &Window, camera: &mut Camera, movement_speed: f32, rotation_speed: f32) -> bool {
+    let mut moved = false;
+
+    let steps = [
+        (Key::W, movement_speed, 0.0, 0.0),
+        (Key::S, -movement_speed, 0.0, 0.0),
+        (Key::D, 0.0, movement_speed, 0.0),
+        (Key::A, 0.0, -movement_speed, 0.0),
+        (Key::Q, 0.0, 0.0, movement_speed),
+        (Key::E, 0.0, 0.0, -movement_speed),
+    ];
+    for (key, forward, right, up) in steps {
+        if window.is_key_down(key) {
+            camera.move_forward(forward);
+            camera.move_right(right);
+            camera.move_up(up);
+            moved = true;
+        }
+    }
+
+    let turns = [
+        (Key::Left, -rotation_speed, 0.0),
+        (Key::Right, rotation_speed, 0.0),
+        (Key::Up, 0.0, rotation_speed),
+        (Key::Down, 0.0, -rotation_speed),
+    ];
+    for (key, yaw, pitch) in turns {
+        if window.is_key_down(key) {
+            camera.look(yaw, pitch);
+            moved = true;
+        }
+    }
+
+    moved
+}
+
+// Plano de corte: 0 del teclado numérico lo activa (quita la mitad del lado
+// de la cámara inicial), + y - lo desplazan, 4/6 y 8/2 lo giran
+fn handle_clip_input(window: &Window, clip_plane: &mut Option<ClipPlane>) {
+    if window.is_key_pressed(Key::NumPad0, KeyRepeat::No) {
+        *clip_plane = match clip_plane {
+            Some(_) => None,
+            None => Some(ClipPlane { normal: Vec3::new(0.0, 0.0, -1.0), offset: 0.0 }),
+        };
+    }
+
+    let Some(plane) = clip_plane else {
+        return;
+    };
+    if window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes) {
+        plane.offset += CLIP_OFFSET_STEP;
+    }
+    if window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes) {
+        plane.offset -= CLIP_OFFSET_STEP;
+    }
+
+    let rotations = [
+        (Key::NumPad4, CLIP_ANGLE_STEP, Vec3::y()),
+        (Key::NumPad6, -CLIP_ANGLE_STEP, Vec3::y()),
+        (Key::NumPad8, CLIP_ANGLE_STEP, Vec3::x()),
+        (Key::NumPad2, -CLIP_ANGLE_STEP, Vec3::x()),
+    ];
+    for (key, angle, axis) in rotations {
+        if window.is_key_pressed(key, KeyRepeat::Yes) {
+            plane.normal = rotate_vec3(&plane.normal, angle, &axis).normalize();
+        }
+    }
+}
+
+// Espacio activa el giro de exhibición, Enter cambia su velocidad
+fn handle_turntable_input(window: &Window, turntable: &mut Turntable) {
+    if window.is_key_pressed(Key::Space, KeyRepeat::No) {
+        turntable.active = !turntable.active;
+    }
+    if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+        let current = TURNTABLE_SPEEDS.iter().position(|&speed| speed == turntable.speed).unwrap_or(0);
+        turntable.speed = TURNTABLE_SPEEDS[(current + 1) % TURNTABLE_SPEEDS.len()];
+    }
+}
+
+// Controles de simulación y depuración
+fn handle_debug_input(window: &Window, uniforms: &mut Uniforms, triangle_count: usize) {
+    // Shift+] y Shift+[ cambian solo la velocidad de las órbitas
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    if shift {
+        if window.is_key_pressed(Key::RightBracket, KeyRepeat::No) {
+            uniforms.orbit_rate = (uniforms.orbit_rate * 2.0).min(64.0);
+        }
+        if window.is_key_pressed(Key::LeftBracket, KeyRepeat::No) {
+            uniforms.orbit_rate = (uniforms.orbit_rate / 2.0).max(0.0625);
+        }
+    }
+
+    // Velocidad de la simulación: ] acelera, [ frena, R invierte el tiempo
+    if !shift && window.is_key_pressed(Key::RightBracket, KeyRepeat::No) {
+        uniforms.time_scale = (uniforms.time_scale * 2.0).clamp(-16.0, 16.0);
+    }
+    if !shift && window.is_key_pressed(Key::LeftBracket, KeyRepeat::No) {
+        let slower = uniforms.time_scale / 2.0;
+        uniforms.time_scale = if slower.abs() < 0.0625 { 0.0625 * slower.signum() } else { slower };
+    }
+    if window.is_key_pressed(Key::R, KeyRepeat::No) {
+        uniforms.time_scale = -uniforms.time_scale;
+    }
+
+    // Rotación propia: F5 la pausa, F6 la frena y F7 la acelera
+    if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+        uniforms.spin_paused = !uniforms.spin_paused;
+    }
+    if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+        uniforms.spin_rate = (uniforms.spin_rate / 2.0).max(0.125);
+    }
+    if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+        uniforms.spin_rate = (uniforms.spin_rate * 2.0).min(8.0);
+    }
+
+    // G cambia a alambre: solo las aristas visibles, todas, y de vuelta al
+    // sombreado normal
+    if window.is_key_pressed(Key::G, KeyRepeat::No) {
+        uniforms.wireframe = match uniforms.wireframe {
+            WireframeMode::Off => WireframeMode::Hidden,
+            WireframeMode::Hidden => WireframeMode::All,
+            WireframeMode::All => WireframeMode::Off,
+        };
+    }
+
+    // \ pinta de rojo las caras traseras para comprobar el orden de los
+    // vértices; Shift+\ deja de descartarlas
+    if window.is_key_pressed(Key::Backslash, KeyRepeat::No) {
+        if shift {
+            uniforms.cull_backfaces = !uniforms.cull_backfaces;
+        } else {
+            uniforms.tint_backfaces = !uniforms.tint_backfaces;
+        }
+    }
+
+    // / tiñe cada planeta con los colores de vértice del modelo
+    if window.is_key_pressed(Key::Slash, KeyRepeat::No) {
+        uniforms.vertex_colors = !uniforms.vertex_colors;
+    }
+
+    // T activa el modo de un solo triángulo, PageUp/PageDown cambian el índice
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+        uniforms.solo_triangle = match uniforms.solo_triangle {
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    if let Some(solo) = uniforms.solo_triangle {
+        if triangle_count == 0 {
+            return;
+        }
+        if window.is_key_pressed(Key::PageUp, KeyRepeat::Yes) {
+            uniforms.solo_triangle = Some((solo + 1) % triangle_count);
+        }
+        if window.is_key_pressed(Key::PageDown, KeyRepeat::Yes) {
+            uniforms.solo_triangle = Some((solo + triangle_count - 1) % triangle_count);
+        }
+    }
+}
+
+// Ajusta la imagen final al tamaño actual de la ventana, y el framebuffer
+// de la escena y las matrices de proyección y viewport a ese tamaño por el
+// factor de supermuestreo (la proyección solo depende de la proporción, así
+// que el encuadre no cambia); devuelve false si la ventana no tiene área
+fn resize_to_window(
+    window: &Window,
+    framebuffer: &mut Framebuffer,
+    display: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    supersample: usize,
+) -> bool {
+    let (width, height) = window.get_size();
+    if width == 0 || height == 0 {
+        return false;
+    }
+
+    if (width, height) != (display.width, display.height) {
+        display.resize(width, height);
+    }
+    let (render_width, render_height) = (width * supersample, height * supersample);
+    if (render_width, render_height) != (framebuffer.width, framebuffer.height) {
+        framebuffer.resize(render_width, render_height);
+        uniforms.projection_matrix = create_perspective_matrix(render_width as f32, render_height as f32);
+        uniforms.viewport_matrix = create_viewport_matrix(render_width as f32, render_height as f32);
+    }
+    true
+}
+
+// Índice del cuerpo dibujado bajo el cursor, escalando la posición del
+// mouse si la ventana no tiene el mismo tamaño que el framebuffer
+fn pick_body(window: &Window, framebuffer: &Framebuffer) -> Option<usize> {
+    let (mouse_x, mouse_y) = window.get_mouse_pos(MouseMode::Discard)?;
+    let (window_width, window_height) = window.get_size();
+    if window_width == 0 || window_height == 0 {
+        return None;
+    }
+
+    let x = (mouse_x * framebuffer.width as f32 / window_width as f32) as usize;
+    let y = (mouse_y * framebuffer.height as f32 / window_height as f32) as usize;
+    framebuffer.id_at(x, y).map(|id| id as usize - 1)
+}
+
+// F1 elige el siguiente parámetro de shader; Home lo sube y End lo baja
+fn handle_param_input(window: &Window, uniforms: &mut Uniforms, selected: &mut usize) {
+    let mut changed = false;
+    if window.is_key_pressed(Key::F1, KeyRepeat::No) {
+        *selected = (*selected + 1) % SHADER_PARAMS.len();
+        changed = true;
+    }
+
+    let shader_param = &SHADER_PARAMS[*selected];
+    let delta = if window.is_key_pressed(Key::Home, KeyRepeat::Yes) {
+        shader_param.step
+    } else if window.is_key_pressed(Key::End, KeyRepeat::Yes) {
+        -shader_param.step
+    } else {
+        0.0
+    };
+    if delta != 0.0 {
+        let value = param(uniforms, shader_param) + delta;
+        uniforms.params.insert(shader_param.name.to_string(), value);
+        changed = true;
+    }
+
+    if changed {
+        println!("{} = {:.2}", shader_param.name, param(uniforms, shader_param));
+    }
+}
+
+// Insert elige el color del planeta seleccionado, Delete el canal y ; ' lo
+// bajan o suben; el cambio se ve en el cuadro siguiente
+fn handle_palette_input(
+    window: &Window,
+    uniforms: &mut Uniforms,
+    colors: &[ColorParam],
+    selected: &mut usize,
+    channel: &mut usize,
+) {
+    let mut changed = false;
+    if window.is_key_pressed(Key::Insert, KeyRepeat::No) {
+        *selected = (*selected + 1) % colors.len();
+        changed = true;
+    }
+    if window.is_key_pressed(Key::Delete, KeyRepeat::No) {
+        *channel = (*channel + 1) % CHANNEL_NAMES.len();
+        changed = true;
+    }
+
+    // Al cambiar de planeta la paleta puede ser más corta
+    let color_param_ref = &colors[*selected % colors.len()];
+    let delta = if window.is_key_pressed(Key::Apostrophe, KeyRepeat::Yes) {
+        PALETTE_STEP
+    } else if window.is_key_pressed(Key::Semicolon, KeyRepeat::Yes) {
+        -PALETTE_STEP
+    } else {
+        0
+    };
+    if delta != 0 {
+        let hex = color_param(uniforms, color_param_ref).to_hex();
+        let shift = 16 - 8 * *channel;
+        let value = ((((hex >> shift) & 0xFF) as i32 + delta).clamp(0, 255)) as u32;
+        let hex = (hex & !(0xFF << shift)) | (value << shift);
+        set_color_param(uniforms, color_param_ref, Color::from_hex(hex));
+        changed = true;
+    }
+
+    if changed {
+        println!("{}", palette_line(uniforms, color_param_ref, *channel));
+    }
+}
+
+fn palette_line(uniforms: &Uniforms, color_param_ref: &ColorParam, channel: usize) -> String {
+    let hex = color_param(uniforms, color_param_ref).to_hex();
+    format!(
+        "{}: {} {} {} CANAL {}",
+        color_param_ref.name, hex >> 16, (hex >> 8) & 0xFF, hex & 0xFF, CHANNEL_NAMES[channel],
+    )
+}
+
+// Carga la escena y todos sus modelos, volviendo a la escena integrada si algo
+// falla. Con --model, ese modelo reemplaza las mallas de todos los cuerpos; si
+// no se puede cargar se conservan las de la escena. `flip_winding` solo se
+// aplica a ese modelo
+fn load_scene(path: &str, model: Option<&str>, flip_winding: bool) -> (Scene, HashMap<String, Vec<Vertex>>) {
+    let scene = match Scene::load(path) {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("{}: {}; usando la escena integrada", path, error);
+            Scene::builtin()
+        }
+    };
+
+    let (mut scene, mut meshes) = match load_meshes(&scene) {
+        Ok(meshes) => (scene, meshes),
+        Err(error) => {
+            eprintln!("{}: {}; usando la escena integrada", path, error);
+            let scene = Scene::builtin();
+            let meshes = load_meshes(&scene).expect("Failed to load obj");
+            (scene, meshes)
+        }
+    };
+
+    if let Some(model) = model {
+        match load_mesh(model, flip_winding) {
+            Ok(vertices) => {
+                for body in &mut scene.bodies {
+                    body.mesh = model.to_string();
+                }
+                meshes = HashMap::from([(model.to_string(), vertices)]);
+            }
+            Err(error) => eprintln!("{}: {}; usando los modelos de la escena", model, error),
+        }
+    }
+
+    (scene, meshes)
+}
+
+fn load_skybox(directory: &str) -> Option<Skybox> {
+    if !std::path::Path::new(directory).is_dir() {
+        return None;
+    }
+
+    match Skybox::load(directory) {
+        Ok(skybox) => Some(skybox),
+        Err(error) => {
+            eprintln!("{}: {}; usando el color de fondo", directory, error);
+            None
+        }
+    }
+}
+
+// Las texturas que faltan simplemente no se usan; las que no se pueden leer
+// se informan y el planeta queda solo procedural
+fn load_textures(directory: &str) -> HashMap<String, Texture> {
+    let mut textures = HashMap::new();
+    for name in TEXTURE_NAMES {
+        let path = format!("{}/{}.png", directory, name);
+        if !std::path::Path::new(&path).is_file() {
+            continue;
+        }
+
+        match Texture::load(&path) {
+            Ok(texture) => {
+                textures.insert(name.to_string(), texture);
+            }
+            Err(error) => eprintln!("{}: {}; superficie procedural", path, error),
+        }
+    }
+    textures
+}
+
+fn load_meshes(scene: &Scene) -> Result<HashMap<String, Vec<Vertex>>, String> {
+    let mut meshes = HashMap::new();
+    for body in &scene.bodies {
+        if !meshes.contains_key(&body.mesh) {
+            let vertices = load_mesh(&body.mesh, false)
+                .map_err(|error| format!("body `{}`, field `mesh`: {} ({})", body.name, body.mesh, error))?;
+            meshes.insert(body.mesh.clone(), vertices);
+        }
+    }
+    Ok(meshes)
+}
+
+// Carga un modelo centrado y escalado a SPHERE_RADIUS; sin triángulos no hay
+// nada que dibujar, así que también cuenta como error
+fn load_mesh(path: &str, flip_winding: bool) -> Result<Vec<Vertex>, String> {
+    // Los archivos grandes informan el avance de la carga
+    let mut last_percent = None;
+    let mut obj = Obj::load_with_progress(path, |read, total| {
+        if total < LARGE_MODEL_BYTES {
+            return;
+        }
+        let percent = read * 100 / total;
+        if last_percent != Some(percent) && percent % 10 == 0 {
+            println!("{}: {}%", path, percent);
+            last_percent = Some(percent);
+        }
+    }).map_err(|error| error.to_string())?;
+    if obj.triangle_count() == 0 {
+        return Err("no triangles".to_string());
+    }
+    obj.normalize(SPHERE_RADIUS);
+    if flip_winding {
+        obj.flip_winding();
+    }
+    println!("{}: {} vértices, {} triángulos", path, obj.vertex_count(), obj.triangle_count());
+    Ok(obj.get_vertex_array())
+}
+
+// Devuelve las nuevas subdivisiones si cambiaron este cuadro
+fn sphere_segments_input(window: &Window, current: Option<u32>) -> Option<u32> {
+    // Con Shift, - y = ajustan el contraste
+    if window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift) {
+        return None;
+    }
+
+    let segments = current.unwrap_or(16);
+    if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+        Some((segments * 2).min(MAX_SPHERE_SEGMENTS / 2))
+    } else if window.is_key_pressed(Key::Minus, KeyRepeat::No) {
+        Some((segments / 2).max(MIN_SPHERE_SEGMENTS))
     } else {
         None
     }
 }
+
+// Shift+1..9 guarda la vista actual, 1..9 la restaura
+fn handle_bookmark_input(window: &Window, bookmarks: &mut Bookmarks, camera: &Camera) {
+    let slot_keys = [
+        Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+        Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+
+    for (slot, key) in slot_keys.iter().enumerate() {
+        if window.is_key_pressed(*key, KeyRepeat::No) {
+            if shift {
+                bookmarks.store(slot, camera);
+            } else {
+                bookmarks.restore(slot, camera);
+            }
+        }
+    }
+}
+
+// 0 recorre los marcadores guardados con una curva suave, o pausa y reanuda
+// el recorrido en curso; Retroceso lo detiene donde esté
+fn handle_tour_input(window: &Window, tour: &mut Option<Tour>, bookmarks: &Bookmarks) {
+    if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
+        match tour {
+            Some(active) => active.paused = !active.paused,
+            None => match bookmarks.path() {
+                Some(path) => {
+                    let duration = TOUR_SECONDS_PER_KEYFRAME * (path.len() - 1).max(1) as f32;
+                    println!("Recorrido por {} marcadores", path.len());
+                    *tour = Some(Tour::new(path, duration));
+                }
+                None => println!("No hay marcadores para recorrer (Shift+1..9 guarda uno)"),
+            },
+        }
+    }
+    if tour.is_some() && window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+        *tour = None;
+        println!("Recorrido detenido");
+    }
+}
+
+// Controles para orbitar la luz alrededor de la escena
+fn handle_light_input(window: &Window, light: &mut Light) {
+    let rotation_speed = PI / 50.0;
+
+    // Shift+I / Shift+O suben y bajan la intensidad, Shift+J / Shift+L
+    // acortan y alargan el día (la órbita automática de la luz)
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    if shift {
+        if window.is_key_pressed(Key::I, KeyRepeat::Yes) {
+            light.intensity = (light.intensity + 0.1).min(4.0);
+        }
+        if window.is_key_pressed(Key::O, KeyRepeat::Yes) {
+            light.intensity = (light.intensity - 0.1).max(0.0);
+        }
+
+        let current = DAY_LENGTHS.iter().position(|&length| length == light.day_length).unwrap_or(0);
+        if window.is_key_pressed(Key::J, KeyRepeat::No) {
+            light.day_length = DAY_LENGTHS[(current + 1).min(DAY_LENGTHS.len() - 1)];
+        }
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            light.day_length = DAY_LENGTHS[current.saturating_sub(1)];
+        }
+        return;
+    }
+
+    if window.is_key_down(Key::J) {
+        light.orbit(rotation_speed, 0.0);
+    }
+    if window.is_key_down(Key::L) {
+        light.orbit(-rotation_speed, 0.0);
+    }
+    if window.is_key_down(Key::I) {
+        light.orbit(0.0, rotation_speed);
+    }
+    if window.is_key_down(Key::O) {
+        light.orbit(0.0, -rotation_speed);
+    }
+}
+
+// Función para obtener el índice del planeta según la tecla presionada. Es
+// la posición del cuerpo en la escena, no su shader: las teclas siguen el
+// orden desde el Sol, pero la escena lista la Tierra (2) antes que Venus (3).
+// Neptuno va en Shift+K, junto a Urano, porque L mueve la luz, y la Luna
+// en Shift+V, junto a la Tierra.
+fn get_planet_key(window: &Window) -> Option<usize> {
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    PLANET_KEYS.iter()
+        .find(|&&key| window.is_key_down(key))
+        .and_then(|&key| planet_for_key(key, shift))
+}
+
+// Teclas de selección en orden de prioridad, ver planet_for_key
+const PLANET_KEYS: [Key; 8] = [Key::Z, Key::X, Key::C, Key::V, Key::B, Key::N, Key::M, Key::K];
+
+// Separada de la ventana para poder probarla
+fn planet_for_key(key: Key, shift: bool) -> Option<usize> {
+    match key {
+        Key::Z => Some(0), // Sol
+        Key::X => Some(1), // Mercurio
+        Key::C => Some(3), // Venus
+        Key::V if shift => Some(9), // Luna
+        Key::V => Some(2), // Tierra
+        Key::B => Some(4), // Marte
+        Key::N => Some(5), // Júpiter
+        Key::M => Some(6), // Saturno
+        Key::K if shift => Some(8), // Neptuno
+        Key::K => Some(7), // Urano
+        _ => None,
+    }
+}
+
+// Nombre con la hora en milisegundos para que dos capturas seguidas no se pisen
+fn screenshot_path() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    format!("screenshot_{}.png", millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planet_keys_select_the_intended_bodies() {
+        let expected = [
+            (Key::Z, false, "Sol"),
+            (Key::X, false, "Mercurio"),
+            (Key::C, false, "Venus"),
+            (Key::V, false, "Tierra"),
+            (Key::V, true, "Luna"),
+            (Key::B, false, "Marte"),
+            (Key::N, false, "Júpiter"),
+            (Key::M, false, "Saturno"),
+            (Key::K, false, "Urano"),
+            (Key::K, true, "Neptuno"),
+        ];
+
+        // La escena incluida y la de assets/ deben listar los cuerpos igual
+        let file = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/scene.toml")).unwrap();
+        for scene in [Scene::builtin(), file] {
+            for (key, shift, name) in expected {
+                let index = planet_for_key(key, shift).unwrap();
+                assert_eq!(scene.bodies[index].name, name, "{:?} (shift: {})", key, shift);
+            }
+        }
+        assert_eq!(planet_for_key(Key::L, false), None);
+        assert!(PLANET_KEYS.iter().all(|&key| planet_for_key(key, false).is_some()));
+    }
+}
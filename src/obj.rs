@@ -1,29 +1,61 @@
 use tobj;
 use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
 use crate::vertex::Vertex;
+use crate::color::Color;
+
+// Limits for the generated UV sphere, so the vertex array stays in the tens of MB
+pub const MIN_SPHERE_SEGMENTS: u32 = 3;
+pub const MAX_SPHERE_SEGMENTS: u32 = 256;
 
 pub struct Obj {
     meshes: Vec<Mesh>,
 }
 
 struct Mesh {
+    // From the `o` or `g` line that started it, see Obj::get_vertex_array_for_group
+    name: String,
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
+    tangents: Vec<Vec3>,
+    // Per-vertex colors from `v x y z r g b` lines, empty when the file has none
+    colors: Vec<Color>,
     indices: Vec<u32>,
 }
 
 impl Obj {
+    // tobj already skips comments, blank lines, CRLF endings and directives
+    // it does not know (s, usemtl, ...); .mtl files are not even opened,
+    // since materials are unused. Every `o` or `g` line starts a new mesh
+    // named after it. Point and line elements are dropped here, otherwise
+    // triangulation turns them into degenerate triangles. Only malformed
+    // vertex or face data fails the load.
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        Self::load_with_progress(filename, |_, _| {})
+    }
+
+    // Same as `load`, calling `progress(bytes_read, file_size)` about every
+    // percent of the file. The file is parsed line by line from a buffered
+    // reader, so memory grows with the mesh and not with the file.
+    pub fn load_with_progress<F: FnMut(u64, u64)>(filename: &str, progress: F) -> Result<Self, tobj::LoadError> {
+        let file = File::open(filename).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+        let total = file.metadata().map_or(0, |metadata| metadata.len());
+        let mut reader = ProgressReader::new(BufReader::new(file), total, progress);
+
+        let (models, _) = tobj::load_obj_buf(&mut reader, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
-            ..Default::default()
-        })?;
+            ignore_points: true,
+            ignore_lines: true,
+        }, |_| Err(tobj::LoadError::OpenFileFailed))?;
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
-            Mesh {
+            let mut mesh = Mesh {
+                name: model.name,
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
@@ -33,30 +65,469 @@ impl Obj {
                 texcoords: mesh.texcoords.chunks(2)
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .collect(),
+                tangents: Vec::new(),
+                colors: mesh.vertex_color.chunks(3)
+                    .map(|c| Color::from_float(c[0], c[1], c[2]))
+                    .collect(),
                 indices: mesh.indices,
+            };
+            if mesh.normals.len() != mesh.vertices.len() {
+                mesh.compute_normals();
             }
+            mesh.compute_tangents();
+            mesh
         }).collect();
 
         Ok(Obj { meshes })
     }
 
+    // Procedural UV sphere centered at the origin. Segment counts are clamped
+    // to MIN_SPHERE_SEGMENTS..=MAX_SPHERE_SEGMENTS.
+    pub fn uv_sphere(latitudes: u32, longitudes: u32, radius: f32) -> Self {
+        let latitudes = latitudes.clamp(MIN_SPHERE_SEGMENTS, MAX_SPHERE_SEGMENTS);
+        let longitudes = longitudes.clamp(MIN_SPHERE_SEGMENTS, MAX_SPHERE_SEGMENTS);
+
+        let ring = longitudes + 1;
+        let vertex_count = ((latitudes + 1) * ring) as usize;
+        let mut vertices = Vec::with_capacity(vertex_count);
+        let mut normals = Vec::with_capacity(vertex_count);
+        let mut texcoords = Vec::with_capacity(vertex_count);
+
+        for i in 0..=latitudes {
+            let theta = PI * i as f32 / latitudes as f32;
+            for j in 0..=longitudes {
+                let phi = 2.0 * PI * j as f32 / longitudes as f32;
+                let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+
+                vertices.push(normal * radius);
+                normals.push(normal);
+                texcoords.push(Vec2::new(j as f32 / longitudes as f32, i as f32 / latitudes as f32));
+            }
+        }
+
+        // Counter-clockwise when seen from outside; the pole rows only get
+        // one triangle per quad since the other would have zero area
+        let mut indices = Vec::with_capacity((latitudes * longitudes * 6) as usize);
+        for i in 0..latitudes {
+            for j in 0..longitudes {
+                let a = i * ring + j;
+                let b = a + ring;
+                if i != 0 {
+                    indices.extend_from_slice(&[a, a + 1, b]);
+                }
+                if i != latitudes - 1 {
+                    indices.extend_from_slice(&[a + 1, b + 1, b]);
+                }
+            }
+        }
+
+        let mut mesh = Mesh {
+            name: String::from("sphere"),
+            vertices,
+            normals,
+            texcoords,
+            tangents: Vec::new(),
+            colors: Vec::new(),
+            indices,
+        };
+        mesh.compute_tangents();
+
+        Obj { meshes: vec![mesh] }
+    }
+
+    // Flat ring in the XZ plane between the `inner` and `outer` radius, for
+    // planetary rings. Both faces are built (normals +Y and -Y), so it shows
+    // from above and below with back-face culling on. u runs once around the
+    // ring and v from the inner edge (0) to the outer one (1).
+    pub fn annulus(inner: f32, outer: f32, segments: u32) -> Self {
+        let segments = segments.clamp(MIN_SPHERE_SEGMENTS, MAX_SPHERE_SEGMENTS);
+        let ring = (segments + 1) * 2;
+
+        let mut vertices = Vec::with_capacity(ring as usize * 2);
+        let mut normals = Vec::with_capacity(ring as usize * 2);
+        let mut texcoords = Vec::with_capacity(ring as usize * 2);
+        for normal in [Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)] {
+            for j in 0..=segments {
+                let u = j as f32 / segments as f32;
+                let (sin, cos) = (2.0 * PI * u).sin_cos();
+                for (radius, v) in [(inner, 0.0), (outer, 1.0)] {
+                    vertices.push(Vec3::new(cos * radius, 0.0, sin * radius));
+                    normals.push(normal);
+                    texcoords.push(Vec2::new(u, v));
+                }
+            }
+        }
+
+        // Counter-clockwise seen from +Y for the top face, reversed for the
+        // bottom one, which starts at index `ring`
+        let mut indices = Vec::with_capacity((segments * 12) as usize);
+        for j in 0..segments {
+            let inner_a = j * 2;
+            let (outer_a, inner_b, outer_b) = (inner_a + 1, inner_a + 2, inner_a + 3);
+            indices.extend_from_slice(&[inner_a, inner_b, outer_a, inner_b, outer_b, outer_a]);
+            indices.extend_from_slice(&[
+                ring + inner_a, ring + outer_a, ring + inner_b,
+                ring + inner_b, ring + outer_a, ring + outer_b,
+            ]);
+        }
+
+        let mut mesh = Mesh {
+            name: String::from("rings"),
+            vertices,
+            normals,
+            texcoords,
+            tangents: Vec::new(),
+            colors: Vec::new(),
+            indices,
+        };
+        mesh.compute_tangents();
+
+        Obj { meshes: vec![mesh] }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.vertices.len()).sum()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum()
+    }
+
+    // Axis-aligned (min, max) corners over all meshes, None for an empty model
+    pub fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        let mut positions = self.meshes.iter().flat_map(|mesh| mesh.vertices.iter());
+        let first = *positions.next()?;
+
+        Some(positions.fold((first, first), |(min, max), v| {
+            (min.inf(v), max.sup(v))
+        }))
+    }
+
+    // Recenters the model on the bounding box center and scales it so its
+    // bounding sphere has the given radius. `load` keeps the file's original
+    // coordinates; call this afterwards to frame arbitrary models.
+    pub fn normalize(&mut self, radius: f32) {
+        let (min, max) = match self.bounding_box() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let center = (min + max) * 0.5;
+
+        let extent = self.meshes.iter()
+            .flat_map(|mesh| mesh.vertices.iter())
+            .map(|v| (v - center).magnitude())
+            .fold(0.0, f32::max);
+        let scale = if extent > 0.0 { radius / extent } else { 1.0 };
+
+        for mesh in &mut self.meshes {
+            for v in &mut mesh.vertices {
+                *v = (*v - center) * scale;
+            }
+        }
+    }
+
+    // Reverses the vertex order of every triangle and negates the normals, for
+    // models exported with the opposite winding convention (inside out here)
+    pub fn flip_winding(&mut self) {
+        for mesh in &mut self.meshes {
+            for triangle in mesh.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+            for normal in &mut mesh.normals {
+                *normal = -*normal;
+            }
+        }
+    }
+
+    // Names of the `o`/`g` groups, in file order
+    pub fn group_names(&self) -> Vec<&str> {
+        self.meshes.iter().map(|mesh| mesh.name.as_str()).collect()
+    }
+
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
+        let mut vertices = Vec::with_capacity(self.meshes.iter().map(|mesh| mesh.indices.len()).sum());
+        for mesh in &self.meshes {
+            mesh.push_vertices(&mut vertices);
+        }
+        vertices
+    }
+
+    // Only the meshes of the `o`/`g` group called `name` (e.g. the rings of a
+    // planet+ring file), None if the file has no such group
+    pub fn get_vertex_array_for_group(&self, name: &str) -> Option<Vec<Vertex>> {
         let mut vertices = Vec::new();
+        let mut found = false;
+        for mesh in self.meshes.iter().filter(|mesh| mesh.name == name) {
+            mesh.push_vertices(&mut vertices);
+            found = true;
+        }
+        found.then_some(vertices)
+    }
+}
 
-        for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
+// Buffered reader that counts the bytes handed to the parser
+struct ProgressReader<R, F> {
+    inner: R,
+    total: u64,
+    read: u64,
+    next_report: u64,
+    progress: F,
+}
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+impl<R, F: FnMut(u64, u64)> ProgressReader<R, F> {
+    fn new(inner: R, total: u64, progress: F) -> Self {
+        ProgressReader { inner, total, read: 0, next_report: 0, progress }
+    }
+
+    fn advance(&mut self, amount: usize) {
+        self.read += amount as u64;
+        if self.read >= self.next_report {
+            (self.progress)(self.read, self.total);
+            self.next_report = self.read + (self.total / 100).max(1);
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64, u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let amount = self.inner.read(buf)?;
+        self.advance(amount);
+        Ok(amount)
+    }
+}
+
+impl<R: BufRead, F: FnMut(u64, u64)> BufRead for ProgressReader<R, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.advance(amount);
+    }
+}
+
+impl Mesh {
+    // One vertex per index, so every three form a triangle. Faces written
+    // without `vt` (v//vn) get zero texture coordinates.
+    fn push_vertices(&self, vertices: &mut Vec<Vertex>) {
+        for &index in &self.indices {
+            let position = self.vertices[index as usize];
+            let normal = self.normal_at(index as usize);
+            let tex_coords = self.texcoords.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec2::new(0.0, 0.0));
+
+            let mut vertex = Vertex::new(position, normal, tex_coords);
+            if let Some(tangent) = self.tangents.get(index as usize) {
+                vertex.tangent = *tangent;
+            }
+            if let Some(color) = self.colors.get(index as usize) {
+                vertex.color = *color;
             }
+            vertices.push(vertex);
         }
+    }
 
-        vertices
+    fn normal_at(&self, index: usize) -> Vec3 {
+        self.normals.get(index).cloned().unwrap_or(Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    // Smooth normals for files without `vn` data: each vertex averages the
+    // normals of its faces, weighted by area, assuming counter-clockwise
+    // winding seen from outside (the OBJ convention)
+    fn compute_normals(&mut self) {
+        let mut accumulated = vec![Vec3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+        for face in self.indices.chunks_exact(3) {
+            let (i0, i1, i2) = (face[0] as usize, face[1] as usize, face[2] as usize);
+            let edge1 = self.vertices[i1] - self.vertices[i0];
+            let edge2 = self.vertices[i2] - self.vertices[i0];
+
+            // Unnormalized, so larger faces count more
+            let face_normal = edge1.cross(&edge2);
+            accumulated[i0] += face_normal;
+            accumulated[i1] += face_normal;
+            accumulated[i2] += face_normal;
+        }
+
+        self.normals = accumulated.iter().map(|normal| {
+            if normal.magnitude() > 1e-12 {
+                normal.normalize()
+            } else {
+                Vec3::new(0.0, 1.0, 0.0)
+            }
+        }).collect();
+    }
+
+    // Per-vertex tangents from position and UV deltas, averaged over the
+    // triangles sharing each vertex and orthogonalized against its normal
+    fn compute_tangents(&mut self) {
+        let mut accumulated = vec![Vec3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+        if self.texcoords.len() == self.vertices.len() {
+            for face in self.indices.chunks(3) {
+                if face.len() < 3 {
+                    continue;
+                }
+                let (i0, i1, i2) = (face[0] as usize, face[1] as usize, face[2] as usize);
+
+                let edge1 = self.vertices[i1] - self.vertices[i0];
+                let edge2 = self.vertices[i2] - self.vertices[i0];
+                let delta_uv1 = self.texcoords[i1] - self.texcoords[i0];
+                let delta_uv2 = self.texcoords[i2] - self.texcoords[i0];
+
+                let det = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+                if det.abs() < 1e-8 {
+                    continue;
+                }
+
+                let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / det;
+                accumulated[i0] += tangent;
+                accumulated[i1] += tangent;
+                accumulated[i2] += tangent;
+            }
+        }
+
+        self.tangents = accumulated.iter().enumerate().map(|(i, tangent)| {
+            let normal = self.normal_at(i);
+
+            // Gram-Schmidt
+            let orthogonal = tangent - normal * normal.dot(tangent);
+            if orthogonal.magnitude() > 1e-6 {
+                orthogonal.normalize()
+            } else {
+                any_perpendicular(&normal)
+            }
+        }).collect();
     }
-}
\ No newline at end of file
+}
+
+// Unit vector perpendicular to `normal`: the tangent of vertices without
+// usable UVs, and of fragments whose interpolated tangent collapsed
+pub fn any_perpendicular(normal: &Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    axis.cross(normal).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes `source` to a temporary file named after the test and hands
+    // its path to `load`
+    fn with_source<T>(name: &str, source: &str, load: impl FnOnce(&str) -> T) -> T {
+        let path = std::env::temp_dir().join(format!("model3d-{}-{}.obj", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let result = load(path.to_str().unwrap());
+        std::fs::remove_file(&path).ok();
+        result
+    }
+
+    fn load_source(name: &str, source: &str) -> Result<Obj, tobj::LoadError> {
+        with_source(name, source, Obj::load)
+    }
+
+    #[test]
+    fn load_skips_comments_blank_lines_and_crlf() {
+        let source = "# exported by a modeling tool\r\n\r\nmtllib scene.mtl\r\no triangle\r\nv 0 0 0 \r\nv 1 0 0\r\n\r\n# the last corner\r\nv 0 1 0\t\r\nusemtl default\r\ns off\r\nf 1 2 3\r\n";
+        let obj = load_source("crlf", source).unwrap();
+        assert_eq!(obj.group_names(), vec!["triangle"]);
+        assert_eq!((obj.vertex_count(), obj.triangle_count()), (3, 1));
+
+        let (min, max) = obj.bounding_box().unwrap();
+        assert_eq!((min, max), (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn load_still_rejects_malformed_vertex_data() {
+        assert!(load_source("malformed", "v 0 zero 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn normal_less_cube_gets_unit_outward_normals() {
+        let source = "\
+v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+f 5 6 7 8\nf 2 1 4 3\nf 6 2 3 7\nf 1 5 8 4\nf 8 7 3 4\nf 1 2 6 5\n";
+        let obj = load_source("cube", source).unwrap();
+        assert_eq!(obj.triangle_count(), 12);
+
+        for vertex in obj.get_vertex_array() {
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5, "normal {:?} is not unit length", vertex.normal);
+            // Corners touched by both triangles of a face lean towards it,
+            // but every smooth normal still points away from the center
+            assert!(vertex.normal.dot(&vertex.position.normalize()) > 0.5, "normal {:?} at {:?}", vertex.normal, vertex.position);
+        }
+    }
+
+    #[test]
+    fn large_generated_obj_streams_in() {
+        // A 256 x 256 vertex grid, a few MB of text
+        let size = 256;
+        let mut source = String::new();
+        for y in 0..size {
+            for x in 0..size {
+                source.push_str(&format!("v {} {} 0\n", x, y));
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let corner = y * size + x + 1;
+                source.push_str(&format!("f {} {} {} {}\n", corner, corner + 1, corner + size + 1, corner + size));
+            }
+        }
+
+        let mut reports = Vec::new();
+        let obj = with_source("large", &source, |path| {
+            Obj::load_with_progress(path, |read, total| reports.push((read, total)))
+        }).unwrap();
+        assert_eq!(obj.vertex_count(), size * size);
+        assert_eq!(obj.triangle_count(), 2 * (size - 1) * (size - 1));
+
+        // Read incrementally: many reports, in order, the last within a
+        // percent of the file size
+        let total = source.len() as u64;
+        assert!(reports.len() >= 50, "only {} progress reports", reports.len());
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(reports.iter().all(|&(read, reported_total)| read <= total && reported_total == total));
+        assert!(reports.last().is_some_and(|&(read, _)| read + total / 100 >= total));
+    }
+
+    #[test]
+    fn groups_load_separately_with_their_uvs() {
+        let source = "\
+o planet\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nf 1/1 2/2 3/3\n\
+o ring\nv 2 0 0\nv 3 0 0\nv 2 1 0\nvt 0.25 0.5\nvt 0.75 0.5\nvt 0.25 1\nf 4/4 5/5 6/6\n";
+        let obj = load_source("groups", source).unwrap();
+        assert_eq!(obj.group_names(), vec!["planet", "ring"]);
+        assert_eq!(obj.get_vertex_array().len(), 6);
+        assert!(obj.get_vertex_array_for_group("moon").is_none());
+
+        // V is flipped on load so 0 is the top row of the image
+        let ring = obj.get_vertex_array_for_group("ring").unwrap();
+        let tex_coords: Vec<Vec2> = ring.iter().map(|vertex| vertex.tex_coords).collect();
+        assert_eq!(tex_coords, vec![Vec2::new(0.25, 0.5), Vec2::new(0.75, 0.5), Vec2::new(0.25, 0.0)]);
+        assert_eq!(ring[0].position, Vec3::new(2.0, 0.0, 0.0));
+
+        let planet = obj.get_vertex_array_for_group("planet").unwrap();
+        assert_eq!(planet[2].tex_coords, Vec2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn faces_without_texture_coordinates_fall_back_to_zero() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n";
+        let vertices = load_source("no-uvs", source).unwrap().get_vertex_array();
+        assert_eq!(vertices.len(), 3);
+        for vertex in vertices {
+            assert_eq!(vertex.tex_coords, Vec2::new(0.0, 0.0));
+            assert_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+            // Without UVs the tangent is still a unit vector across the normal
+            assert!((vertex.tangent.magnitude() - 1.0).abs() < 1e-5 && vertex.tangent.dot(&vertex.normal).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use nalgebra_glm::{Vec3, Mat4, mat4_to_mat3};
+use crate::framebuffer::Framebuffer;
+
+const GIZMO_MARGIN: i32 = 50;
+const GIZMO_LENGTH: f32 = 30.0;
+const CROSSHAIR_SIZE: i32 = 6;
+
+// Draws the world X (red), Y (green) and Z (blue) axes in the bottom-left
+// corner, rotated by the camera but not translated, plus a center crosshair
+pub fn draw_axis_gizmo(framebuffer: &mut Framebuffer, view_matrix: &Mat4) {
+    let rotation = mat4_to_mat3(view_matrix);
+    let origin_x = GIZMO_MARGIN;
+    let origin_y = framebuffer.height as i32 - GIZMO_MARGIN;
+
+    let mut axes = [
+        (rotation * Vec3::new(1.0, 0.0, 0.0), 0xFF0000),
+        (rotation * Vec3::new(0.0, 1.0, 0.0), 0x00FF00),
+        (rotation * Vec3::new(0.0, 0.0, 1.0), 0x0000FF),
+    ];
+
+    // Draw the axis pointing away from the camera first
+    axes.sort_by(|a, b| a.0.z.total_cmp(&b.0.z));
+
+    for (axis, color) in axes.iter() {
+        let end_x = origin_x + (axis.x * GIZMO_LENGTH).round() as i32;
+        let end_y = origin_y - (axis.y * GIZMO_LENGTH).round() as i32;
+        framebuffer.set_current_color(*color);
+        framebuffer.line(origin_x, origin_y, end_x, end_y);
+    }
+
+    let center_x = framebuffer.width as i32 / 2;
+    let center_y = framebuffer.height as i32 / 2;
+    framebuffer.set_current_color(0xFFFFFF);
+    framebuffer.line(center_x - CROSSHAIR_SIZE, center_y, center_x + CROSSHAIR_SIZE, center_y);
+    framebuffer.line(center_x, center_y - CROSSHAIR_SIZE, center_x, center_y + CROSSHAIR_SIZE);
+}
+
+// Glyphs are 3x5 cells drawn at GLYPH_SCALE pixels per cell
+const GLYPH_SCALE: usize = 2;
+const GLYPH_ADVANCE: usize = 4 * GLYPH_SCALE;
+const LINE_HEIGHT: usize = 7 * GLYPH_SCALE;
+const PANEL_MARGIN: usize = 8;
+const PANEL_PADDING: usize = 6;
+
+// Rows of a 3x5 glyph, top to bottom, leftmost column in the high bit.
+// Lowercase is drawn as uppercase; characters without a glyph are blank.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        _ => [0; 5],
+    }
+}
+
+// Draws `text` with its top-left corner at (x, y), clipped to the buffer
+pub fn draw_text(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, color: u32) {
+    for (i, c) in text.chars().enumerate() {
+        let origin_x = x + i * GLYPH_ADVANCE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    for dx in 0..GLYPH_SCALE {
+                        let px = origin_x + column * GLYPH_SCALE + dx;
+                        let py = y + row * GLYPH_SCALE + dy;
+                        if px < framebuffer.width && py < framebuffer.height {
+                            framebuffer.write_pixel(py * framebuffer.width + px, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Lists `lines` in the top-left corner over a darkened box
+pub fn draw_panel(framebuffer: &mut Framebuffer, lines: &[String]) {
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let width = (columns * GLYPH_ADVANCE + 2 * PANEL_PADDING).min(framebuffer.width.saturating_sub(PANEL_MARGIN));
+    let height = (lines.len() * LINE_HEIGHT + 2 * PANEL_PADDING).min(framebuffer.height.saturating_sub(PANEL_MARGIN));
+
+    for y in PANEL_MARGIN..PANEL_MARGIN + height {
+        for x in PANEL_MARGIN..PANEL_MARGIN + width {
+            let index = y * framebuffer.width + x;
+            let darkened = (framebuffer.buffer[index] >> 2) & 0x3F3F3F;
+            framebuffer.write_pixel(index, darkened);
+        }
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        let x = PANEL_MARGIN + PANEL_PADDING;
+        let y = PANEL_MARGIN + PANEL_PADDING + i * LINE_HEIGHT;
+        draw_text(framebuffer, x, y, line, 0xFFFFFF);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::framebuffer::Framebuffer;
+
+// Saves consecutive frames as numbered PNGs while active
+pub struct Recorder {
+    directory: PathBuf,
+    max_frames: usize,
+    frame: usize,
+    active: bool,
+}
+
+impl Recorder {
+    pub fn new(directory: &str, max_frames: usize) -> Self {
+        Recorder {
+            directory: PathBuf::from(directory),
+            max_frames,
+            frame: 0,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    // Starting a new recording restarts the numbering at frame_00000
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        if self.active {
+            self.frame = 0;
+        }
+    }
+
+    // Saves the frame if recording; stops on its own after `max_frames`
+    pub fn capture(&mut self, framebuffer: &Framebuffer) -> io::Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(format!("frame_{:05}.png", self.frame));
+        framebuffer.save_png(&path.to_string_lossy())?;
+
+        self.frame += 1;
+        if self.frame >= self.max_frames {
+            self.active = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,697 @@
+use std::ops::AddAssign;
+
+use nalgebra_glm::{Mat4, Vec3};
+use rayon::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::fragment::Fragment;
+use crate::vertex::Vertex;
+use crate::triangle::{for_each_fragment, is_back_facing};
+use crate::shaders::{vertex_shader, select_shader, select_shader_hdr, palette, color_param, RING_SHADER};
+use crate::uniforms::{Uniforms, Occluder, WireframeMode, create_model_matrix};
+use crate::line::for_each_line_pixel;
+use crate::clip::{clip_near, past_near_plane};
+use crate::color::Color;
+use crate::obj::Obj;
+use crate::scene::Body;
+use crate::lod::{Lod, screen_radius};
+
+// Vertices per rayon task; smaller batches cost more in scheduling than the
+// vertex shader itself
+const VERTEX_BATCH: usize = 256;
+
+// Fragments gathered before shading them in parallel; bounds the memory a
+// full-screen body needs while keeping every rayon call worth its overhead
+const FRAGMENT_BATCH: usize = 4096;
+// Fragments per rayon task while shading
+const SHADING_BATCH: usize = 64;
+
+// Segments around a generated ring; it is flat, so only the outline shows them
+const RING_SEGMENTS: u32 = 128;
+
+// How far in front of its own faces a hidden-line wireframe edge is tested,
+// in depth-buffer units
+const WIREFRAME_DEPTH_BIAS: f32 = 1e-5;
+
+// Work done by one or more `render` calls; add them up for a whole frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub vertices: usize,
+    pub triangles: usize,
+    // Dropped before rasterizing: entirely in front of the near plane, or
+    // with a vertex the vertex shader could not transform (NaN)
+    pub triangles_skipped: usize,
+    // Cut at the near plane and drawn in pieces, see clip::clip_near
+    pub triangles_clipped: usize,
+    // Dropped before rasterizing because they face away from the camera
+    pub triangles_culled: usize,
+    // Produced by the rasterizer, including those later rejected
+    pub fragments: usize,
+    // Passed the bounds, depth range and clip plane checks and reached the shader
+    pub fragments_shaded: usize,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.vertices += other.vertices;
+        self.triangles += other.triangles;
+        self.triangles_skipped += other.triangles_skipped;
+        self.triangles_clipped += other.triangles_clipped;
+        self.triangles_culled += other.triangles_culled;
+        self.fragments += other.fragments;
+        self.fragments_shaded += other.fragments_shaded;
+    }
+}
+
+// Runs the vertex shader over every vertex in parallel. The output keeps the
+// input order, so consecutive triples are still the mesh's triangles.
+pub fn transform_vertices(vertex_array: &[Vertex], uniforms: &Uniforms) -> Vec<Vertex> {
+    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
+    vertex_array
+        .par_iter()
+        .with_min_len(VERTEX_BATCH)
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect_into_vec(&mut transformed_vertices);
+    transformed_vertices
+}
+
+// Runs the planet shader selected by `index` over every fragment in
+// parallel, returning color, emission and alpha in the input order
+pub fn shade_fragments(fragments: &[Fragment], uniforms: &Uniforms, index: usize) -> Vec<(Color, f32, f32)> {
+    let mut shaded = Vec::with_capacity(fragments.len());
+    fragments
+        .par_iter()
+        .with_min_len(SHADING_BATCH)
+        .map(|fragment| select_shader(index, fragment, uniforms))
+        .collect_into_vec(&mut shaded);
+    shaded
+}
+
+// Like `shade_fragments`, with the unquantized colors of select_shader_hdr
+pub fn shade_fragments_hdr(fragments: &[Fragment], uniforms: &Uniforms, index: usize) -> Vec<([f32; 3], f32, f32)> {
+    let mut shaded = Vec::with_capacity(fragments.len());
+    fragments
+        .par_iter()
+        .with_min_len(SHADING_BATCH)
+        .map(|fragment| select_shader_hdr(index, fragment, uniforms))
+        .collect_into_vec(&mut shaded);
+    shaded
+}
+
+fn mix(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t)
+}
+
+// Fragments that passed every check, waiting to be shaded. Each keeps the
+// triangle it came from and whether that triangle faces away, for the debug
+// tints applied after shading.
+#[derive(Default)]
+struct FragmentBatch {
+    fragments: Vec<Fragment>,
+    sources: Vec<(usize, bool)>,
+}
+
+impl FragmentBatch {
+    fn push(&mut self, fragment: Fragment, triangle_index: usize, back_facing: bool) {
+        self.fragments.push(fragment);
+        self.sources.push((triangle_index, back_facing));
+    }
+
+    fn is_full(&self) -> bool {
+        self.fragments.len() >= FRAGMENT_BATCH
+    }
+
+    // Shades the batch in parallel, then writes it in the order the fragments
+    // were produced so the depth test resolves exactly as a serial loop would.
+    // A float buffer gets the unquantized colors, so dithering has something
+    // below 8 bits to spread.
+    fn flush(&mut self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, index: usize) {
+        let highlight = Color::new(255, 0, 255);
+        let backface_tint = Color::new(255, 0, 0);
+
+        if framebuffer.is_hdr() {
+            let shaded = shade_fragments_hdr(&self.fragments, uniforms, index);
+            for ((fragment, &(triangle_index, tint_back)), (rgb, emission, alpha)) in
+                self.fragments.iter().zip(&self.sources).zip(shaded)
+            {
+                // Debug: los mismos tintes, sin redondear
+                let rgb = match uniforms.solo_triangle {
+                    Some(solo) if solo == triangle_index => mix(rgb, highlight.to_float(), 0.6),
+                    Some(_) => rgb.map(|channel| channel * 0.25),
+                    None => rgb,
+                };
+                let rgb = if tint_back { mix(rgb, backface_tint.to_float(), 0.7) } else { rgb };
+
+                // Only HDR lets emission push a color past white
+                let rgb = if uniforms.hdr { rgb.map(|channel| channel * (1.0 + emission)) } else { rgb };
+                framebuffer.set_current_color_hdr(rgb);
+                write_fragment(framebuffer, fragment, uniforms, emission, alpha);
+            }
+        } else {
+            let shaded = shade_fragments(&self.fragments, uniforms, index);
+            for ((fragment, &(triangle_index, tint_back)), (shaded_color, emission, alpha)) in
+                self.fragments.iter().zip(&self.sources).zip(shaded)
+            {
+                // Debug: resaltar un solo triángulo y atenuar el resto
+                let shaded_color = match uniforms.solo_triangle {
+                    Some(solo) if solo == triangle_index => shaded_color.lerp(&highlight, 0.6),
+                    Some(_) => shaded_color * 0.25,
+                    None => shaded_color,
+                };
+
+                // Debug: marcar en rojo las caras que se consideran traseras
+                let shaded_color = if tint_back {
+                    shaded_color.lerp(&backface_tint, 0.7)
+                } else {
+                    shaded_color
+                };
+
+                framebuffer.set_current_color(shaded_color.to_hex());
+                write_fragment(framebuffer, fragment, uniforms, emission, alpha);
+            }
+        }
+
+        self.fragments.clear();
+        self.sources.clear();
+    }
+}
+
+// Depth-tested write of one shaded fragment with the framebuffer's current color
+fn write_fragment(framebuffer: &mut Framebuffer, fragment: &Fragment, uniforms: &Uniforms, emission: f32, alpha: f32) {
+    let depth = (fragment.depth + uniforms.depth_bias).clamp(0.0, 1.0);
+    framebuffer.set_current_emission(emission);
+    let (x, y) = (fragment.position.x as usize, fragment.position.y as usize);
+    if alpha < 1.0 {
+        framebuffer.blend(x, y, depth, alpha);
+    } else {
+        framebuffer.point(x, y, depth);
+    }
+}
+
+// Draws one frame of `vertex_array` into the framebuffer, shading every
+// fragment with the planet shader selected by `index`. No window needed.
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) -> RenderStats {
+    let transformed_vertices = transform_vertices(vertex_array, uniforms);
+    let mut stats = RenderStats {
+        vertices: transformed_vertices.len(),
+        triangles: transformed_vertices.len() / 3,
+        ..RenderStats::default()
+    };
+
+    if uniforms.wireframe != WireframeMode::Off {
+        render_wireframe(framebuffer, uniforms, &transformed_vertices, index, &mut stats);
+        return stats;
+    }
+
+    let cull = uniforms.cull_backfaces && !uniforms.tint_backfaces && uniforms.clip_plane.is_none();
+    let mut batch = FragmentBatch::default();
+
+    for (triangle_index, tri) in transformed_vertices.chunks_exact(3).enumerate() {
+        if tri.iter().any(|vertex| vertex.clip_position.w.is_nan()) {
+            stats.triangles_skipped += 1;
+            continue;
+        }
+
+        if past_near_plane(tri) {
+            draw_triangle(framebuffer, uniforms, tri, triangle_index, cull, &mut batch, &mut stats);
+        } else {
+            // Cut at the near plane: the part in front of it is drawn as one
+            // or two smaller triangles, the rest would divide by w <= 0
+            let pieces = clip_near(tri, &uniforms.viewport_matrix);
+            if pieces.is_empty() {
+                stats.triangles_skipped += 1;
+            } else {
+                stats.triangles_clipped += 1;
+            }
+            for piece in &pieces {
+                draw_triangle(framebuffer, uniforms, piece, triangle_index, cull, &mut batch, &mut stats);
+            }
+        }
+
+        if batch.is_full() {
+            batch.flush(framebuffer, uniforms, index);
+        }
+    }
+    batch.flush(framebuffer, uniforms, index);
+
+    stats
+}
+
+// Edges of every triangle, near-clipped like the filled path, in the first
+// color of the shader's palette. With WireframeMode::Hidden back faces are
+// culled and the triangles first fill the depth buffer alone; the edges are
+// then depth tested against it, nudged forward by WIREFRAME_DEPTH_BIAS so
+// they win against their own faces. With All every edge is drawn on top.
+fn render_wireframe(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    transformed_vertices: &[Vertex],
+    index: usize,
+    stats: &mut RenderStats,
+) {
+    let hidden = uniforms.wireframe == WireframeMode::Hidden;
+    let cull = hidden && uniforms.cull_backfaces && uniforms.clip_plane.is_none();
+
+    let mut triangles: Vec<[Vertex; 3]> = Vec::new();
+    for tri in transformed_vertices.chunks_exact(3) {
+        if tri.iter().any(|vertex| vertex.clip_position.w.is_nan()) {
+            stats.triangles_skipped += 1;
+            continue;
+        }
+
+        let pieces = if past_near_plane(tri) {
+            vec![[tri[0].clone(), tri[1].clone(), tri[2].clone()]]
+        } else {
+            let pieces = clip_near(tri, &uniforms.viewport_matrix);
+            if pieces.is_empty() {
+                stats.triangles_skipped += 1;
+            } else {
+                stats.triangles_clipped += 1;
+            }
+            pieces
+        };
+        for piece in pieces {
+            if cull && is_back_facing(&piece[0], &piece[1], &piece[2]) {
+                stats.triangles_culled += 1;
+            } else {
+                triangles.push(piece);
+            }
+        }
+    }
+
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    if hidden {
+        for tri in &triangles {
+            for_each_fragment(&tri[0], &tri[1], &tri[2], width, height, |fragment| {
+                if let Some(plane) = &uniforms.clip_plane {
+                    if !plane.keeps(&fragment.world_position) {
+                        return;
+                    }
+                }
+                if (0.0..=1.0).contains(&fragment.depth) {
+                    let (x, y) = (fragment.position.x as usize, fragment.position.y as usize);
+                    framebuffer.depth_point(x, y, fragment.depth + uniforms.depth_bias);
+                }
+            });
+        }
+    }
+
+    framebuffer.set_current_color(color_param(uniforms, &palette(index)[0]).to_hex());
+    framebuffer.set_current_emission(0.0);
+    for tri in &triangles {
+        for (a, b) in [(&tri[0], &tri[1]), (&tri[1], &tri[2]), (&tri[2], &tri[0])] {
+            for_each_line_pixel(a, b, width, height, |x, y, depth| {
+                stats.fragments += 1;
+                // Negative infinity passes every depth test
+                let depth = if hidden { depth + uniforms.depth_bias - WIREFRAME_DEPTH_BIAS } else { f32::NEG_INFINITY };
+                framebuffer.point(x, y, depth);
+            });
+        }
+    }
+}
+
+// Rasterizes one triangle that lies entirely past the near plane, queueing
+// the fragments that should be shaded; `triangle_index` is its position in
+// the mesh, for the solo mode
+fn draw_triangle(
+    framebuffer: &Framebuffer,
+    uniforms: &Uniforms,
+    tri: &[Vertex],
+    triangle_index: usize,
+    cull: bool,
+    batch: &mut FragmentBatch,
+    stats: &mut RenderStats,
+) {
+    let back_facing = is_back_facing(&tri[0], &tri[1], &tri[2]);
+    if cull && back_facing {
+        stats.triangles_culled += 1;
+        return;
+    }
+    let tint_back = uniforms.tint_backfaces && back_facing;
+
+    for_each_fragment(&tri[0], &tri[1], &tri[2], framebuffer.width, framebuffer.height, |fragment| {
+        stats.fragments += 1;
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        // Outside 0..1 the fragment is in front of the near plane or past
+        // the far one; the bias only shifts it for the depth test
+        let in_depth_range = (0.0..=1.0).contains(&fragment.depth);
+        if x < framebuffer.width && y < framebuffer.height && in_depth_range {
+            // Cross-section: discard what lies on the negative side of the plane
+            if let Some(plane) = &uniforms.clip_plane {
+                if !plane.keeps(&fragment.world_position) {
+                    return;
+                }
+            }
+
+            stats.fragments_shaded += 1;
+            batch.push(fragment, triangle_index, tint_back);
+        }
+    });
+}
+
+// Draws one body of the scene centered at `translation` with its own spin,
+// tilt and scale; `orientation` then turns it about its center (identity for
+// none). The body's object ID is `index + 1`, 0 being the background.
+pub fn render_body(
+    framebuffer: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    body: &Body,
+    index: usize,
+    translation: Vec3,
+    orientation: &Mat4,
+    vertex_array: &[Vertex],
+) -> RenderStats {
+    uniforms.model_matrix = body_matrix(body, uniforms.spin_time, translation, orientation);
+
+    framebuffer.set_current_id(index as u32 + 1);
+    render(framebuffer, uniforms, vertex_array, body.shader)
+}
+
+// Draws every body at its world position (`positions[i]` for `bodies[i]`,
+// e.g. from Body::orbit_position), lit from the sun at the origin. The other
+// bodies, except the sun itself, act as eclipse occluders. Bodies that look
+// small get a coarser sphere from `lod`, the rest the mesh from `full_mesh`.
+pub fn render_system<'a>(
+    framebuffer: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    bodies: &[Body],
+    positions: &[Vec3],
+    orientation: &Mat4,
+    lod: &mut Lod,
+    full_mesh: impl Fn(&Body) -> &'a [Vertex],
+) -> RenderStats {
+    let light_position = uniforms.light.position;
+    uniforms.light.position = Vec3::new(0.0, 0.0, 0.0);
+
+    let mut stats = RenderStats::default();
+    for (index, (body, &position)) in bodies.iter().zip(positions).enumerate() {
+        uniforms.set_occluders(position, &system_occluders(bodies, positions, index, lod.radius()));
+
+        let pixel_radius = screen_radius(
+            position,
+            lod.radius() * body.scale,
+            &uniforms.view_matrix,
+            &uniforms.projection_matrix,
+            framebuffer.height as f32,
+        );
+        let vertex_array = lod.select(index, pixel_radius, full_mesh(body));
+        stats += render_body(framebuffer, uniforms, body, index, position, orientation, vertex_array);
+    }
+
+    uniforms.light.position = light_position;
+    stats
+}
+
+// Draws the rings of every body that has them, placed and lit as in
+// render_system; `planet_radius` is the radius of the bodies' mesh. Call it
+// after everything opaque, background included, since the rings blend over
+// what is already in the framebuffer.
+pub fn render_system_rings(
+    framebuffer: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    bodies: &[Body],
+    positions: &[Vec3],
+    orientation: &Mat4,
+    planet_radius: f32,
+) -> RenderStats {
+    let light_position = uniforms.light.position;
+    uniforms.light.position = Vec3::new(0.0, 0.0, 0.0);
+
+    let mut stats = RenderStats::default();
+    for (index, (body, &position)) in bodies.iter().zip(positions).enumerate() {
+        if body.rings.is_some() {
+            uniforms.set_occluders(position, &system_occluders(bodies, positions, index, planet_radius));
+            stats += render_rings(framebuffer, uniforms, body, position, orientation, planet_radius);
+        }
+    }
+
+    uniforms.light.position = light_position;
+    stats
+}
+
+// Draws the rings of `body` (nothing if it has none) in its equatorial
+// plane, with the same placement as render_body. The body joins the
+// occluders so it casts its shadow on them. The flat ring mesh is rebuilt on
+// every call, which costs little next to shading it.
+pub fn render_rings(
+    framebuffer: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    body: &Body,
+    translation: Vec3,
+    orientation: &Mat4,
+    planet_radius: f32,
+) -> RenderStats {
+    let rings = match body.rings {
+        Some(rings) => rings,
+        None => return RenderStats::default(),
+    };
+    let vertex_array = Obj::annulus(planet_radius * rings.inner, planet_radius * rings.outer, RING_SEGMENTS)
+        .get_vertex_array();
+
+    let occluders = uniforms.occluders.clone();
+    let mut with_body = vec![Occluder { center: translation, radius: planet_radius * body.scale }];
+    with_body.extend_from_slice(&occluders);
+    uniforms.set_occluders(translation, &with_body);
+    let displacement = uniforms.displacement;
+    uniforms.displacement = 0.0;
+
+    // Same model matrix as the body, so the ring tilts and turns with it
+    uniforms.model_matrix = body_matrix(body, uniforms.spin_time, translation, orientation);
+    let stats = render(framebuffer, uniforms, &vertex_array, RING_SHADER);
+
+    uniforms.displacement = displacement;
+    uniforms.occluders = occluders;
+    stats
+}
+
+// Spin, tilt and scale of `body` at `spin_time`, then `orientation` about
+// its center, then the move to `translation`
+fn body_matrix(body: &Body, spin_time: f32, translation: Vec3, orientation: &Mat4) -> Mat4 {
+    let rotation = Vec3::from(body.rotation_at(spin_time));
+    let own = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), body.scale, rotation);
+    nalgebra_glm::translation(&translation) * orientation * own
+}
+
+// Eclipse candidates while drawing `bodies[index]`: every other body except
+// the sun, as spheres of `radius` times their scale
+fn system_occluders(bodies: &[Body], positions: &[Vec3], index: usize, radius: f32) -> Vec<Occluder> {
+    bodies.iter().zip(positions).enumerate()
+        .filter(|(other, (other_body, _))| *other != index && other_body.shader != 0)
+        .map(|(_, (other_body, &center))| Occluder { center, radius: radius * other_body.scale })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec4};
+    use crate::scene::Scene;
+    use crate::uniforms::{create_perspective_matrix, create_viewport_matrix, create_view_matrix};
+
+    fn uniforms_for(framebuffer: &Framebuffer) -> Uniforms {
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        let mut uniforms = Uniforms::new(create_perspective_matrix(width, height), create_viewport_matrix(width, height));
+        uniforms.displacement = 0.0;
+        uniforms.view_matrix = create_view_matrix(Vec3::new(0.0, 0.0, 30.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        uniforms
+    }
+
+    // Width and height in pixels of what was drawn
+    fn drawn_extent(framebuffer: &Framebuffer) -> (usize, usize) {
+        let covered = |x: usize, y: usize| framebuffer.id_at(x, y).is_some();
+        let columns: Vec<usize> = (0..framebuffer.width).filter(|&x| (0..framebuffer.height).any(|y| covered(x, y))).collect();
+        let rows: Vec<usize> = (0..framebuffer.height).filter(|&y| (0..framebuffer.width).any(|x| covered(x, y))).collect();
+        match (columns.first(), columns.last(), rows.first(), rows.last()) {
+            (Some(left), Some(right), Some(top), Some(bottom)) => (right - left + 1, bottom - top + 1),
+            _ => (0, 0),
+        }
+    }
+
+    #[test]
+    fn sphere_stays_round_at_any_aspect_ratio() {
+        let sphere = Obj::uv_sphere(24, 48, 3.5).get_vertex_array();
+        for (width, height) in [(320, 180), (180, 320), (210, 90)] {
+            let mut framebuffer = Framebuffer::new(width, height);
+            let uniforms = uniforms_for(&framebuffer);
+            framebuffer.set_current_id(1);
+            render(&mut framebuffer, &uniforms, &sphere, 1);
+
+            let (drawn_width, drawn_height) = drawn_extent(&framebuffer);
+            assert!(drawn_width > 10, "{}x{}: nothing drawn", width, height);
+            assert!(drawn_width.abs_diff(drawn_height) <= 2, "{}x{}: sphere drawn {}x{}", width, height, drawn_width, drawn_height);
+        }
+    }
+
+    #[test]
+    fn hdr_keeps_emissive_output_above_one() {
+        let sphere = Obj::uv_sphere(24, 48, 3.5).get_vertex_array();
+        let draw_sun = |hdr: bool| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            framebuffer.set_hdr(true);
+            let mut uniforms = uniforms_for(&framebuffer);
+            uniforms.hdr = hdr;
+            render(&mut framebuffer, &uniforms, &sphere, 0);
+            framebuffer.resolve_to_u32(false);
+            Color::from_hex(framebuffer.get(32, 32)).to_float().iter().sum::<f32>()
+        };
+
+        let clamped = draw_sun(false);
+        let unclamped = draw_sun(true);
+        assert!(unclamped > clamped + 0.1, "HDR sun {} should outshine the 8-bit one {}", unclamped, clamped);
+    }
+
+    #[test]
+    fn dither_breaks_up_the_shading_gradient() {
+        // Saturn's flat color, so the only gradient is the diffuse falloff
+        let sphere = Obj::uv_sphere(24, 48, 3.5).get_vertex_array();
+        let draw_saturn = |dither: bool| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            framebuffer.set_hdr(true);
+            let uniforms = uniforms_for(&framebuffer);
+            render(&mut framebuffer, &uniforms, &sphere, 6);
+            framebuffer.resolve_to_u32(dither);
+            framebuffer.buffer
+        };
+
+        let plain = draw_saturn(false);
+        let dithered = draw_saturn(true);
+        let steps: Vec<i32> = plain.iter().zip(&dithered)
+            .flat_map(|(&a, &b)| {
+                let (a, b) = (Color::from_hex(a).to_float(), Color::from_hex(b).to_float());
+                [0, 1, 2].map(|i| ((b[i] - a[i]) * 255.0).round() as i32)
+            })
+            .collect();
+        let changed = steps.iter().filter(|&&step| step != 0).count();
+        assert!(changed > 100, "dithering changed only {} channels", changed);
+        assert!(steps.iter().all(|step| step.abs() <= 1));
+    }
+
+    #[test]
+    fn vertex_on_the_camera_plane_leaves_no_nan() {
+        let mut framebuffer = Framebuffer::new(64, 64);
+        let uniforms = uniforms_for(&framebuffer);
+        // The eye sits at z = 30, so the last vertex has w = 0 after projection
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let triangle = [Vec3::new(-2.0, -2.0, 0.0), Vec3::new(2.0, -2.0, 0.0), Vec3::new(5.0, 5.0, 30.0)]
+            .map(|position| Vertex::new(position, normal, Vec2::new(0.0, 0.0)));
+
+        let stats = render(&mut framebuffer, &uniforms, &triangle, 1);
+        assert_eq!(stats.triangles_clipped, 1);
+        assert!(framebuffer.zbuffer.iter().any(|&depth| depth != f32::INFINITY), "nothing drawn");
+        assert!(framebuffer.zbuffer.iter().all(|&depth| depth == f32::INFINITY || (0.0..=1.0).contains(&depth)));
+    }
+
+    #[test]
+    fn depth_bias_wins_against_a_coincident_triangle() {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let triangle = [Vec3::new(-4.0, -4.0, 0.0), Vec3::new(4.0, -4.0, 0.0), Vec3::new(0.0, 4.0, 0.0)]
+            .map(|position| Vertex::new(position, normal, Vec2::new(0.0, 0.0)));
+        let draw_twice = |bias: f32| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            let mut uniforms = uniforms_for(&framebuffer);
+            framebuffer.set_current_id(1);
+            render(&mut framebuffer, &uniforms, &triangle, 1);
+            uniforms.depth_bias = bias;
+            framebuffer.set_current_id(2);
+            render(&mut framebuffer, &uniforms, &triangle, 1);
+            framebuffer.id_at(32, 32)
+        };
+
+        assert_eq!(draw_twice(0.0), Some(1));
+        assert_eq!(draw_twice(-1e-4), Some(2));
+    }
+
+    #[test]
+    fn receding_floor_is_interpolated_in_perspective() {
+        let framebuffer = Framebuffer::new(160, 120);
+        let uniforms = uniforms_for(&framebuffer);
+        // Floor below the eye from 10 units in front of it to 130 units away
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let corners = [(-5.0, 20.0), (5.0, 20.0), (5.0, -100.0), (-5.0, 20.0), (5.0, -100.0), (-5.0, -100.0)]
+            .map(|(x, z)| Vertex::new(Vec3::new(x, -3.0, z), normal, Vec2::new(0.0, 0.0)));
+        let transformed = transform_vertices(&corners, &uniforms);
+        let fragments: Vec<Fragment> = transformed.chunks_exact(3)
+            .flat_map(|tri| crate::triangle::triangle(&tri[0], &tri[1], &tri[2], framebuffer.width, framebuffer.height))
+            .collect();
+
+        // Where the floor's halfway line (z = -40) lands on screen, the
+        // fragments must come from halfway along it. Interpolating in screen
+        // space would give about z = -90 there; one pixel row spans ~6 units.
+        let midpoint = Vec4::new(0.0, -3.0, -40.0, 1.0);
+        let clip = uniforms.projection_matrix * uniforms.view_matrix * midpoint;
+        let screen = uniforms.viewport_matrix * (clip / clip.w);
+        let (x, y) = (screen.x.floor(), screen.y.floor());
+        let fragment = fragments.iter().find(|fragment| fragment.position == Vec2::new(x, y)).unwrap();
+        assert!((fragment.vertex_position.z - midpoint.z).abs() < 10.0, "midline sampled at z = {}", fragment.vertex_position.z);
+    }
+
+    #[test]
+    fn culling_drops_the_hidden_half_of_a_cube() {
+        // Faces wound counter-clockwise seen from outside, as quads of corner indices
+        let corner = |index: usize| Vec3::new(
+            if index & 1 == 0 { -2.0 } else { 2.0 },
+            if index & 2 == 0 { -2.0 } else { 2.0 },
+            if index & 4 == 0 { -2.0 } else { 2.0 },
+        );
+        let faces = [[4, 5, 7, 6], [1, 0, 2, 3], [5, 1, 3, 7], [0, 4, 6, 2], [6, 7, 3, 2], [0, 1, 5, 4]];
+        let cube: Vec<Vertex> = faces.iter()
+            .flat_map(|face| [face[0], face[1], face[2], face[0], face[2], face[3]])
+            .map(|index| Vertex::new(corner(index), corner(index).normalize(), Vec2::new(0.0, 0.0)))
+            .collect();
+
+        let draw = |cull: bool| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            let mut uniforms = uniforms_for(&framebuffer);
+            // Turned so three faces show, none of them edge-on
+            uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, Vec3::new(0.5, 0.6, 0.0));
+            uniforms.cull_backfaces = cull;
+            render(&mut framebuffer, &uniforms, &cube, 1)
+        };
+
+        let (culled, kept) = (draw(true), draw(false));
+        assert_eq!((culled.triangles, culled.triangles_culled), (12, 6));
+        assert_eq!(kept.triangles_culled, 0);
+        assert!(culled.fragments < kept.fragments);
+    }
+
+    #[test]
+    fn parallel_shading_matches_a_single_thread() {
+        let sphere = Obj::uv_sphere(24, 48, 3.5).get_vertex_array();
+        let draw_with = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                let mut framebuffer = Framebuffer::new(120, 90);
+                let uniforms = uniforms_for(&framebuffer);
+                render(&mut framebuffer, &uniforms, &sphere, 2);
+                framebuffer
+            })
+        };
+
+        let (serial, parallel) = (draw_with(1), draw_with(4));
+        assert!(serial.buffer == parallel.buffer, "colors differ between one and four threads");
+        assert!(serial.zbuffer == parallel.zbuffer, "depths differ between one and four threads");
+    }
+
+    #[test]
+    fn moon_transform_orbits_at_its_distance_from_earth() {
+        let scene = Scene::builtin();
+        let earth = scene.bodies.iter().position(|body| body.name == "Tierra").unwrap();
+        let moon = scene.bodies.iter().position(|body| body.name == "Luna").unwrap();
+        let moon_body = &scene.bodies[moon];
+
+        for time in [0.0, 1.3, 7.9] {
+            let earth_position = Vec3::from(scene.orbit_position(earth, time));
+            let moon_position = Vec3::from(scene.orbit_position(moon, time));
+            let model = body_matrix(moon_body, time, moon_position, &Mat4::identity());
+
+            // The model matrix carries the moon's center to its world position,
+            // one orbit radius away from Earth, with its own small scale
+            let center = (model * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+            assert!((center - moon_position).magnitude() < 1e-4);
+            assert!(((center - earth_position).magnitude() - moon_body.orbit_radius).abs() < 1e-3, "t = {}", time);
+            let surface = (model * Vec4::new(1.0, 0.0, 0.0, 1.0)).xyz();
+            assert!(((surface - center).magnitude() - moon_body.scale).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::shaders::SHADER_COUNT;
+
+pub const DEFAULT_MESH: &str = "assets/model/sphere.obj";
+
+// A body in the scene file. Everything except `name` and `shader` is optional.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Body {
+    pub name: String,
+    pub shader: usize,
+    #[serde(default = "default_mesh")]
+    pub mesh: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    // Axial tilt in degrees, about the Z axis
+    #[serde(default)]
+    pub tilt: f32,
+    // Spin about the tilted axis, radians per second
+    #[serde(default)]
+    pub spin_speed: f32,
+    #[serde(default)]
+    pub orbit_radius: f32,
+    #[serde(default)]
+    pub orbit_speed: f32,
+    // Name of the body this one orbits, declared earlier in the scene; none
+    // orbits the origin
+    #[serde(default)]
+    pub parent: Option<String>,
+    // Flat rings around the equator, drawn translucent with the ring shader
+    #[serde(default)]
+    pub rings: Option<Rings>,
+}
+
+// Edges of a ring system, in radii of the body it surrounds
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rings {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    #[serde(rename = "body")]
+    pub bodies: Vec<Body>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "could not read scene file: {}", error),
+            SceneError::Parse(error) => write!(f, "invalid scene file: {}", error),
+            SceneError::Invalid(message) => write!(f, "invalid scene file: {}", message),
+        }
+    }
+}
+
+fn default_mesh() -> String {
+    DEFAULT_MESH.to_string()
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl Body {
+    // Euler angles for the model matrix at `time`: the fixed tilt is applied
+    // after the spin, so the body turns about its tilted axis
+    pub fn rotation_at(&self, time: f32) -> [f32; 3] {
+        [
+            self.rotation[0],
+            self.rotation[1] + self.spin_speed * time,
+            self.rotation[2] + self.tilt.to_radians(),
+        ]
+    }
+
+    // Position on a circular orbit in the XZ plane around the origin at
+    // `orbit_time` (see Uniforms::orbit_time); for a body with a parent this
+    // is relative to the parent, see Scene::orbit_position
+    pub fn orbit_position(&self, orbit_time: f32) -> [f32; 3] {
+        let angle = self.orbit_speed * orbit_time;
+        [self.orbit_radius * angle.cos(), 0.0, self.orbit_radius * angle.sin()]
+    }
+}
+
+impl Scene {
+    pub fn load(path: &str) -> Result<Self, SceneError> {
+        let source = fs::read_to_string(path).map_err(SceneError::Io)?;
+        Scene::parse(&source)
+    }
+
+    // World position of `bodies[index]` at `orbit_time`: its own orbit
+    // around the position of its parent, or around the origin without one
+    pub fn orbit_position(&self, index: usize, orbit_time: f32) -> [f32; 3] {
+        let body = &self.bodies[index];
+        let [x, y, z] = body.orbit_position(orbit_time);
+        match self.parent_index(body) {
+            Some(parent) => {
+                let [px, py, pz] = self.orbit_position(parent, orbit_time);
+                [px + x, py + y, pz + z]
+            }
+            None => [x, y, z],
+        }
+    }
+
+    fn parent_index(&self, body: &Body) -> Option<usize> {
+        let parent = body.parent.as_ref()?;
+        self.bodies.iter().position(|other| &other.name == parent)
+    }
+
+    // TOML syntax errors carry their own line/column; semantic errors name
+    // the offending body and field
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        let scene: Scene = toml::from_str(source).map_err(SceneError::Parse)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    fn validate(&self) -> Result<(), SceneError> {
+        if self.bodies.is_empty() {
+            return Err(SceneError::Invalid("the scene needs at least one [[body]]".to_string()));
+        }
+
+        for (index, body) in self.bodies.iter().enumerate() {
+            let invalid = |field: &str, problem: String| {
+                SceneError::Invalid(format!("body {} (`{}`), field `{}`: {}", index + 1, body.name, field, problem))
+            };
+
+            if body.shader >= SHADER_COUNT {
+                return Err(invalid("shader", format!("{} is out of range 0..{}", body.shader, SHADER_COUNT)));
+            }
+            if !(body.scale.is_finite() && body.scale > 0.0) {
+                return Err(invalid("scale", format!("must be positive, got {}", body.scale)));
+            }
+            if !(body.orbit_radius.is_finite() && body.orbit_radius >= 0.0) {
+                return Err(invalid("orbit_radius", format!("must be zero or positive, got {}", body.orbit_radius)));
+            }
+            if !body.orbit_speed.is_finite() {
+                return Err(invalid("orbit_speed", "must be a finite number".to_string()));
+            }
+            if body.rotation.iter().any(|angle| !angle.is_finite()) {
+                return Err(invalid("rotation", "angles must be finite numbers".to_string()));
+            }
+            if !body.tilt.is_finite() {
+                return Err(invalid("tilt", "must be a finite number".to_string()));
+            }
+            if !body.spin_speed.is_finite() {
+                return Err(invalid("spin_speed", "must be a finite number".to_string()));
+            }
+            // Parents come first, so positions resolve in one pass and a body
+            // can never end up orbiting itself
+            if let Some(parent) = &body.parent {
+                if !self.bodies[..index].iter().any(|other| &other.name == parent) {
+                    return Err(invalid("parent", format!("`{}` is not a body declared before this one", parent)));
+                }
+            }
+            if let Some(rings) = body.rings {
+                if !(rings.inner.is_finite() && rings.outer.is_finite() && 0.0 < rings.inner && rings.inner < rings.outer) {
+                    return Err(invalid("rings", format!("needs 0 < inner < outer, got {} and {}", rings.inner, rings.outer)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Built-in solar system, used when no scene file is given or it is invalid
+    pub fn builtin() -> Self {
+        let body = |name: &str, shader: usize, scale: f32, tilt: f32, spin_speed: f32, orbit_radius: f32, orbit_speed: f32| Body {
+            name: name.to_string(),
+            shader,
+            mesh: default_mesh(),
+            scale,
+            rotation: [0.0; 3],
+            tilt,
+            spin_speed,
+            orbit_radius,
+            orbit_speed,
+            parent: None,
+            rings: None,
+        };
+
+        Scene {
+            bodies: vec![
+                body("Sol", 0, 1.5, 7.25, 0.24, 0.0, 0.0),
+                body("Mercurio", 1, 1.0, 0.03, 0.12, 10.0, 1.2),
+                body("Tierra", 2, 1.0, 23.4, 1.2, 26.0, 0.6),
+                body("Venus", 3, 1.0, 177.4, 0.06, 18.0, 0.9),
+                body("Marte", 4, 1.0, 25.2, 1.14, 34.0, 0.48),
+                body("Júpiter", 5, 1.0, 3.1, 3.0, 44.0, 0.3),
+                Body {
+                    rings: Some(Rings { inner: 1.24, outer: 2.27 }),
+                    ..body("Saturno", 6, 1.0, 26.7, 2.7, 54.0, 0.24)
+                },
+                body("Urano", 7, 1.0, 97.8, 1.68, 64.0, 0.18),
+                body("Neptuno", 9, 1.0, 28.3, 1.8, 74.0, 0.12),
+                Body {
+                    parent: Some("Tierra".to_string()),
+                    ..body("Luna", 10, 0.27, 6.7, 0.3, 5.0, 3.0)
+                },
+            ],
+        }
+    }
+}
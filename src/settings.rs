@@ -0,0 +1,150 @@
+use std::f32::consts::TAU;
+
+use nalgebra_glm::{Mat4, Vec3};
+
+use crate::uniforms::{Uniforms, WireframeMode};
+
+// Supersampling factors offered, see RenderSettings::supersample
+pub const SUPERSAMPLE_FACTORS: [usize; 3] = [1, 2, 4];
+
+// What the main loop draws: the selected body alone at the origin, or every
+// body of the scene at its orbital position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewMode {
+    Single,
+    System,
+}
+
+// Post-processing and overlay switches owned by the main loop
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub fxaa: bool,
+    pub dither: bool,
+    pub show_gizmo: bool,
+    pub overdraw: bool,
+    pub tonemap: bool,
+    pub exposure: f32,
+    // Final tonal adjustment, see Color::adjust; 1.0 leaves the image as is
+    pub brightness: f32,
+    pub contrast: f32,
+    pub hdr: bool,
+    pub show_panel: bool,
+    // The scene is rendered at this many times the window size per axis and
+    // box-filtered down, one of SUPERSAMPLE_FACTORS; 1 is off
+    pub supersample: usize,
+    // Glow around emissive surfaces, see Framebuffer::apply_bloom
+    pub bloom: bool,
+    // Procedural stars behind the scene when there is no skybox
+    pub stars: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            fxaa: false,
+            dither: false,
+            show_gizmo: false,
+            overdraw: false,
+            tonemap: false,
+            exposure: 1.0,
+            brightness: 1.0,
+            contrast: 1.0,
+            hdr: false,
+            show_panel: false,
+            supersample: 1,
+            bloom: true,
+            stars: true,
+        }
+    }
+}
+
+// Everything photo mode overrides, so it can be put back exactly
+#[derive(Clone, Copy, Debug)]
+struct SavedState {
+    settings: RenderSettings,
+    noise_samples: usize,
+    solo_triangle: Option<usize>,
+    wireframe: WireframeMode,
+}
+
+// Clean, high quality capture mode: turns on every quality feature and
+// hides debug overlays, then restores the previous state when turned off
+#[derive(Default)]
+pub struct PhotoMode {
+    saved: Option<SavedState>,
+}
+
+impl PhotoMode {
+    pub fn new() -> Self {
+        PhotoMode { saved: None }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    pub fn toggle(&mut self, settings: &mut RenderSettings, uniforms: &mut Uniforms) {
+        match self.saved.take() {
+            Some(saved) => {
+                *settings = saved.settings;
+                uniforms.noise_samples = saved.noise_samples;
+                uniforms.solo_triangle = saved.solo_triangle;
+                uniforms.wireframe = saved.wireframe;
+            }
+            None => {
+                self.saved = Some(SavedState {
+                    settings: *settings,
+                    noise_samples: uniforms.noise_samples,
+                    solo_triangle: uniforms.solo_triangle,
+                    wireframe: uniforms.wireframe,
+                });
+
+                settings.fxaa = true;
+                settings.supersample = 2;
+                settings.bloom = true;
+                settings.dither = true;
+                settings.tonemap = true;
+                settings.hdr = true;
+                settings.show_gizmo = false;
+                settings.overdraw = false;
+                settings.show_panel = false;
+                uniforms.noise_samples = 8;
+                uniforms.solo_triangle = None;
+                uniforms.wireframe = WireframeMode::Off;
+            }
+        }
+    }
+}
+
+// Showcase mode: the camera stays put while everything drawn turns about the
+// world Y axis. The angle is kept when stopped so the model does not snap back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Turntable {
+    pub active: bool,
+    pub angle: f32,
+    // Radians per second, independent of the simulation time scale
+    pub speed: f32,
+}
+
+impl Turntable {
+    pub fn new(speed: f32) -> Self {
+        Turntable { active: false, angle: 0.0, speed }
+    }
+
+    // Turns by `dt` seconds worth of `speed`
+    pub fn advance(&mut self, dt: f32) {
+        if self.active {
+            self.angle = (self.angle + self.speed * dt) % TAU;
+        }
+    }
+
+    // Rotation applied after the body's own model matrix
+    pub fn matrix(&self) -> Mat4 {
+        nalgebra_glm::rotation(self.angle, &Vec3::y())
+    }
+
+    // Where a world-space point ends up, e.g. an orbital position
+    pub fn rotate(&self, point: Vec3) -> Vec3 {
+        nalgebra_glm::rotate_y_vec3(&point, self.angle)
+    }
+}
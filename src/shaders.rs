@@ -1,22 +1,155 @@
-use nalgebra_glm::{Vec3, Vec4, Mat3, mat4_to_mat3};
+use std::f32::consts::PI;
+use fastnoise_lite::FastNoiseLite;
+use nalgebra_glm::{Vec3, Vec4, mat4_to_mat3};
 use crate::vertex::Vertex;
-use crate::Uniforms;
+use crate::uniforms::{Uniforms, create_normal_matrix};
 use crate::fragment::Fragment;
-use crate::color::Color;
+use crate::color::{Color, Gradient};
 use crate::light::Light;
-use rand::Rng;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
+use crate::crater::crater_profile;
+
+// Smallest |w| divided by in the perspective divide
+const W_EPSILON: f32 = 1e-5;
+
+// Intensidad mínima para que el lado nocturno no quede negro del todo
+const AMBIENT_INTENSITY: f32 = 0.1;
+
+// Number of indices handled by select_shader
+pub const SHADER_COUNT: usize = 11;
+
+// Índices de select_shader cuyos shaders piden su ruido con noise_for
+const SUN_SHADER: usize = 0;
+const MERCURY_SHADER: usize = 1;
+const EARTH_SHADER: usize = 2;
+const VENUS_SHADER: usize = 3;
+const MARS_SHADER: usize = 4;
+const JUPITER_SHADER: usize = 5;
+const URANUS_SHADER: usize = 7;
+// También lo usa render_rings para dibujar los anillos
+pub const RING_SHADER: usize = 8;
+const NEPTUNE_SHADER: usize = 9;
+const MOON_SHADER: usize = 10;
+
+// Emisión del sol, la única superficie que brilla con luz propia
+const SUN_EMISSION: f32 = 1.0;
+
+// Tuning value a shader reads through `param`, overridable at runtime via
+// `uniforms.params`; `step` is the increment used by the live controls
+pub struct ShaderParam {
+  pub name: &'static str,
+  pub default: f32,
+  pub step: f32,
+}
 
-pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-  // Transformación de posición base
-  let position = Vec4::new(
-      vertex.position.x,
-      vertex.position.y,
-      vertex.position.z,
-      1.0,
-  );
+const EARTH_ZOOM: ShaderParam = ShaderParam { name: "earth.zoom", default: 15.0, step: 1.0 };
+const EARTH_OCEAN_THRESHOLD: ShaderParam = ShaderParam { name: "earth.ocean_threshold", default: -0.3, step: 0.05 };
+const JUPITER_BAND_ZOOM: ShaderParam = ShaderParam { name: "jupiter.band_zoom", default: 10.0, step: 1.0 };
+// How far the turbulent noise bends Jupiter's band edges (0 = straight bands)
+const JUPITER_TURBULENCE: ShaderParam = ShaderParam { name: "jupiter.turbulence", default: 1.2, step: 0.1 };
+// Noise below this value is Mercury's darker smooth plains
+const MERCURY_PLAINS_THRESHOLD: ShaderParam = ShaderParam { name: "mercury.plains_threshold", default: -0.2, step: 0.05 };
+// How much the rocky micro-detail darkens or brightens steep slopes (0 = off)
+const MARS_RIM_DETAIL: ShaderParam = ShaderParam { name: "mars.rim_detail", default: 0.15, step: 0.05 };
+const MERCURY_RIM_DETAIL: ShaderParam = ShaderParam { name: "mercury.rim_detail", default: 0.15, step: 0.05 };
+// Noise above this value is the Moon's dark basalt maria
+const MOON_MARE_THRESHOLD: ShaderParam = ShaderParam { name: "moon.mare_threshold", default: 0.3, step: 0.05 };
+// 0 = single-octave clouds from the shared noise, 1 = fractal cloud noise
+const EARTH_CLOUD_DETAIL: ShaderParam = ShaderParam { name: "earth.cloud_detail", default: 1.0, step: 0.25 };
+const VENUS_CLOUD_OPACITY: ShaderParam = ShaderParam { name: "venus.cloud_opacity", default: 0.9, step: 0.05 };
+// Cuánto del relieve procedural se conserva sobre una textura cargada
+const EARTH_ALBEDO_DETAIL: ShaderParam = ShaderParam { name: "earth.albedo_detail", default: 0.25, step: 0.05 };
+const MARS_ALBEDO_DETAIL: ShaderParam = ShaderParam { name: "mars.albedo_detail", default: 0.35, step: 0.05 };
+
+// Every parameter that can be tuned live, in the order the controls cycle
+pub const SHADER_PARAMS: [ShaderParam; 12] = [
+  EARTH_ZOOM,
+  EARTH_OCEAN_THRESHOLD,
+  EARTH_CLOUD_DETAIL,
+  JUPITER_BAND_ZOOM,
+  JUPITER_TURBULENCE,
+  MERCURY_PLAINS_THRESHOLD,
+  MARS_RIM_DETAIL,
+  MERCURY_RIM_DETAIL,
+  VENUS_CLOUD_OPACITY,
+  EARTH_ALBEDO_DETAIL,
+  MARS_ALBEDO_DETAIL,
+  MOON_MARE_THRESHOLD,
+];
+
+pub fn param(uniforms: &Uniforms, param: &ShaderParam) -> f32 {
+  uniforms.params.get(param.name).copied().unwrap_or(param.default)
+}
+
+// Base color of a shader, editable live like a ShaderParam. The override is
+// kept in `uniforms.params` as the packed 0xRRGGBB value (exact in an f32),
+// so reading a color costs a single lookup.
+pub struct ColorParam {
+  pub name: &'static str,
+  pub default: u32,
+}
+
+const SUN_BASE: ColorParam = ColorParam { name: "sun.base", default: 0xFFC832 };
+const SUN_HIGHLIGHT: ColorParam = ColorParam { name: "sun.highlight", default: 0xFFFF96 };
+const MERCURY_BASE: ColorParam = ColorParam { name: "mercury.base", default: 0xA9A9A9 };
+const MERCURY_CRATER: ColorParam = ColorParam { name: "mercury.crater", default: 0x696969 };
+const EARTH_LAND: ColorParam = ColorParam { name: "earth.land", default: 0x228B22 };
+const EARTH_OCEAN: ColorParam = ColorParam { name: "earth.ocean", default: 0x1E90FF };
+const EARTH_DEEP_OCEAN: ColorParam = ColorParam { name: "earth.deep_ocean", default: 0x1969D2 };
+const EARTH_SNOW: ColorParam = ColorParam { name: "earth.snow", default: 0xFFFAFA };
+const EARTH_CLOUD: ColorParam = ColorParam { name: "earth.cloud", default: 0xFFFFFF };
+const VENUS_SURFACE: ColorParam = ColorParam { name: "venus.surface", default: 0xDAA520 };
+const VENUS_HAZE: ColorParam = ColorParam { name: "venus.haze", default: 0xECC46A };
+const VENUS_CLOUD_TOP: ColorParam = ColorParam { name: "venus.cloud_top", default: 0xFFE4B5 };
+const VENUS_DECK_DARK: ColorParam = ColorParam { name: "venus.deck_dark", default: 0xF0D6A0 };
+const VENUS_DECK_LIGHT: ColorParam = ColorParam { name: "venus.deck_light", default: 0xFFF0CD };
+const MARS_BASALT: ColorParam = ColorParam { name: "mars.basalt", default: 0x462314 };
+const MARS_BASE: ColorParam = ColorParam { name: "mars.base", default: 0x8B4513 };
+const MARS_ROCK: ColorParam = ColorParam { name: "mars.rock", default: 0xA9561E };
+const MARS_HIGHLANDS: ColorParam = ColorParam { name: "mars.highlands", default: 0xCD7846 };
+const MARS_CRATER: ColorParam = ColorParam { name: "mars.crater", default: 0x69361E };
+const JUPITER_DARK_BAND: ColorParam = ColorParam { name: "jupiter.dark_band", default: 0xCD853F };
+const JUPITER_LIGHT_BAND: ColorParam = ColorParam { name: "jupiter.light_band", default: 0xFFDEAD };
+const JUPITER_STORM: ColorParam = ColorParam { name: "jupiter.storm", default: 0xFF4500 };
+const SATURN_PLANET: ColorParam = ColorParam { name: "saturn.planet", default: 0xFFE1B4 };
+const SATURN_RING: ColorParam = ColorParam { name: "saturn.ring", default: 0xDCDCDC };
+const URANUS_BASE: ColorParam = ColorParam { name: "uranus.base", default: 0xADD8E6 };
+const URANUS_HIGHLIGHT: ColorParam = ColorParam { name: "uranus.highlight", default: 0xE0FFFF };
+const NEPTUNE_BASE: ColorParam = ColorParam { name: "neptune.base", default: 0x000080 };
+const NEPTUNE_HIGHLIGHT: ColorParam = ColorParam { name: "neptune.highlight", default: 0x4682B4 };
+const MOON_HIGHLANDS: ColorParam = ColorParam { name: "moon.highlands", default: 0xC8C8C3 };
+const MOON_MARE: ColorParam = ColorParam { name: "moon.mare", default: 0x6E6E73 };
+const MOON_CRATER: ColorParam = ColorParam { name: "moon.crater", default: 0x7D7D7D };
+
+// Editable colors of each select_shader index
+pub const PALETTES: [&[ColorParam]; SHADER_COUNT] = [
+  &[SUN_BASE, SUN_HIGHLIGHT],
+  &[MERCURY_BASE, MERCURY_CRATER],
+  &[EARTH_LAND, EARTH_OCEAN, EARTH_DEEP_OCEAN, EARTH_SNOW, EARTH_CLOUD],
+  &[VENUS_SURFACE, VENUS_HAZE, VENUS_CLOUD_TOP, VENUS_DECK_DARK, VENUS_DECK_LIGHT],
+  &[MARS_BASALT, MARS_BASE, MARS_ROCK, MARS_HIGHLANDS, MARS_CRATER],
+  &[JUPITER_DARK_BAND, JUPITER_LIGHT_BAND, JUPITER_STORM],
+  &[SATURN_PLANET, SATURN_RING],
+  &[URANUS_BASE, URANUS_HIGHLIGHT],
+  &[SATURN_RING],
+  &[NEPTUNE_BASE, NEPTUNE_HIGHLIGHT],
+  &[MOON_HIGHLANDS, MOON_MARE, MOON_CRATER],
+];
+
+// Palette of a shader index; like select_shader, unknown indices use the sun's
+pub fn palette(index: usize) -> &'static [ColorParam] {
+  PALETTES.get(index).copied().unwrap_or(PALETTES[0])
+}
 
+pub fn color_param(uniforms: &Uniforms, param: &ColorParam) -> Color {
+  let hex = uniforms.params.get(param.name).map_or(param.default, |&value| value as u32);
+  Color::from_hex(hex)
+}
+
+pub fn set_color_param(uniforms: &mut Uniforms, param: &ColorParam, color: Color) {
+  uniforms.params.insert(param.name.to_string(), color.to_hex() as f32);
+}
+
+pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   // Zoom para el relieve
   let zoom = 5.0;
   let displacement_amount = uniforms.noise.get_noise_3d(
@@ -26,18 +159,24 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   );
 
   // Desplazamiento a lo largo de la normal del vértice
-  let displaced_position = vertex.position + vertex.normal * displacement_amount * 0.5;
+  let displaced_position = vertex.position + vertex.normal * displacement_amount * uniforms.displacement;
 
   // Transformación del vértice desplazado
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * Vec4::new(
+  let world_position = uniforms.model_matrix * Vec4::new(
       displaced_position.x,
       displaced_position.y,
       displaced_position.z,
       1.0,
   );
+  let transformed = uniforms.projection_matrix * uniforms.view_matrix * world_position;
 
-  // División en perspectiva
-  let w = transformed.w;
+  // División en perspectiva; con w ≈ 0 (vértice sobre el plano de la cámara)
+  // se limita w para no producir infinitos, render descarta esos triángulos
+  let w = if transformed.w.abs() < W_EPSILON {
+      W_EPSILON.copysign(transformed.w)
+  } else {
+      transformed.w
+  };
   let ndc_position = Vec4::new(
       transformed.x / w,
       transformed.y / w,
@@ -48,117 +187,235 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   // Aplicar la matriz de viewport
   let screen_position = uniforms.viewport_matrix * ndc_position;
 
-  // Transformar la normal
+  // Transformar la normal con la inversa transpuesta de la matriz de modelo
   let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
-  let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-  let transformed_normal = normal_matrix * vertex.normal;
+  let normal_matrix = create_normal_matrix(&uniforms.model_matrix);
+  let transformed_normal = (normal_matrix * vertex.normal).normalize();
 
-  // Crear un nuevo vértice con atributos transformados
+  // La tangente sigue a la superficie, por lo que usa la matriz de modelo
+  let transformed_tangent = model_mat3 * vertex.tangent;
+
+  // Crear un nuevo vértice con atributos transformados; `position` y `normal`
+  // se conservan en espacio de objeto, los `transformed_*` en espacio de mundo
   Vertex {
       position: vertex.position,
       normal: vertex.normal,
       tex_coords: vertex.tex_coords,
+      tangent: vertex.tangent,
       color: vertex.color,
       transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
+      clip_position: transformed,
+      world_position: world_position.xyz(),
       transformed_normal,
+      transformed_tangent,
   }
 }
 
-
-pub fn select_shader(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// Color del fragmento, cuánta luz propia emite (0 = superficie normal,
+// 1 = fuente brillante; no depende de la iluminación) y su opacidad (1 =
+// opaco, menos se mezcla con lo que ya hay detrás)
+pub fn select_shader(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32, f32) {
   let sun_light = Light::new(
     Vec3::new(0.0, 0.0, 0.0),         // Posición en el origen
     Color::new(255, 255, 150),       // Color amarillento
     1.5                               // Intensidad de la luz
   );
 
+  let (color, alpha) = match lit_surface(index, fragment, uniforms) {
+      Some((surface, alpha)) => (apply_diffuse(surface, fragment, &uniforms.light), alpha),
+      None => (sun_shader(fragment, uniforms, &sun_light), 1.0),
+  };
+
+  // Tinte opcional con el color de vértice (blanco si el modelo no trae)
+  let color = if uniforms.vertex_colors {
+      color.blend_multiply(&fragment.color)
+  } else {
+      color
+  };
+
+  // El sol emite su propia luz, los demás pueden quedar en un eclipse;
+  // la umbra no llega a negro para que el cuerpo se siga distinguiendo
+  if index == 0 {
+      (color, SUN_EMISSION, alpha)
+  } else {
+      (color * eclipse_light(fragment, uniforms).max(0.15), 0.0, alpha)
+  }
+}
+
+// Como select_shader, pero en punto flotante para el búfer HDR: la luz
+// difusa, el degradado del sol, el tinte y el eclipse varían suavemente
+// dentro de una cara y redondearlos a 8 bits dejaría bandas que el tramado
+// ya no puede repartir
+pub fn select_shader_hdr(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> ([f32; 3], f32, f32) {
+  let (rgb, alpha) = match lit_surface(index, fragment, uniforms) {
+      Some((surface, alpha)) => (apply_diffuse_hdr(surface, fragment, &uniforms.light), alpha),
+      None => {
+          let (base_color, highlight_color, noise_value) = sun_surface(fragment, uniforms);
+          (base_color.lerp_float(&highlight_color, noise_value), 1.0)
+      }
+  };
+
+  let rgb = if uniforms.vertex_colors {
+      let tint = fragment.color.to_float();
+      [0, 1, 2].map(|i| rgb[i] * tint[i])
+  } else {
+      rgb
+  };
+
+  if index == 0 {
+      (rgb, SUN_EMISSION, alpha)
+  } else {
+      let light = eclipse_light(fragment, uniforms).max(0.15);
+      (rgb.map(|channel| channel * light), 0.0, alpha)
+  }
+}
 
+// Superficie sin iluminar de cada índice y su opacidad, antes de la luz
+// difusa; None para el sol (y los índices desconocidos), que no la recibe
+fn lit_surface(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> Option<(Color, f32)> {
   match index {
-      0 => sun_shader(fragment, uniforms, &sun_light),       // El Sol
-      1 => mercury_shader(fragment, uniforms),   // Mercurio (puedes crear este shader)
-      2 => earth_shader(fragment, uniforms),     // La Tierra
-      3 => venus_shader(fragment, uniforms),     // Venus (puedes crear este shader)
-      4 => mars_shader(fragment, uniforms),      // Marte
-      5 => jupiter_shader(fragment, uniforms),   // Júpiter
-      6 => saturn_shader(fragment, uniforms),    // Saturno
-      7 => uranus_shader(fragment, uniforms),    // Urano (puedes crear este shader)
-      _ => sun_shader(fragment, uniforms, &sun_light),       // Shader por defecto
+      1 => Some((mercury_surface(fragment, uniforms), 1.0)),   // Mercurio
+      2 => Some((earth_surface(fragment, uniforms), 1.0)),     // La Tierra
+      3 => Some((venus_surface(fragment, uniforms), 1.0)),     // Venus
+      4 => Some((mars_surface(fragment, uniforms), 1.0)),      // Marte
+      5 => Some((jupiter_surface(fragment, uniforms), 1.0)),   // Júpiter
+      6 => Some((saturn_surface(fragment, uniforms), 1.0)),    // Saturno
+      7 => Some((uranus_surface(fragment, uniforms), 1.0)),    // Urano
+      8 => Some(ring_surface(fragment, uniforms)),             // Anillos, translúcidos
+      9 => Some((neptune_surface(fragment, uniforms), 1.0)),   // Neptuno
+      10 => Some((moon_surface(fragment, uniforms), 1.0)),     // La Luna
+      _ => None,                                               // El Sol y el shader por defecto
   }
 }
 
-fn apply_lighting(fragment: &Fragment, light: &Light) -> f32 {
-  let light_direction = (light.position - fragment.vertex_position).normalize();
-  let distance = (light.position - fragment.vertex_position).magnitude();
-  let attenuation = 1.0 / (distance * distance);
-  let dot_product = fragment.normal.dot(&light_direction);
-  (dot_product * light.intensity * attenuation).max(0.0)
+// Fracción de luz que llega al fragmento (1 = iluminado, 0 = umbra). Se
+// lanza un rayo hacia la luz y se prueba contra las esferas de
+// `uniforms.occluders`; el borde se suaviza con una penumbra del 20% del radio
+pub fn eclipse_light(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  if uniforms.occluders.is_empty() {
+      return 1.0;
+  }
+
+  let world = fragment.world_position;
+  let to_light = uniforms.light.position - world;
+  let light_distance = to_light.magnitude();
+  if light_distance <= f32::EPSILON {
+      return 1.0;
+  }
+  let direction = to_light / light_distance;
+
+  uniforms.occluders.iter().fold(1.0, |light, occluder| {
+      // Punto del rayo más cercano al centro, solo entre el fragmento y la luz
+      let t = (occluder.center - world).dot(&direction);
+      if t <= 0.0 || t >= light_distance {
+          return light;
+      }
+      let distance = (world + direction * t - occluder.center).magnitude();
+
+      let inner = occluder.radius * 0.8;
+      let outer = occluder.radius * 1.2;
+      let x = ((distance - inner) / (outer - inner)).clamp(0.0, 1.0);
+      light * (x * x * (3.0 - 2.0 * x))
+  })
+}
+
+// Difuso de Lambert con la normal interpolada y la posición en el mundo,
+// teñido por el color de la luz; nunca baja de AMBIENT_INTENSITY
+pub fn apply_diffuse(base: Color, fragment: &Fragment, light: &Light) -> Color {
+  base.blend_multiply(&light.color) * diffuse_intensity(fragment, light)
+}
+
+// Como apply_diffuse, sin redondear a 8 bits; satura en 1.0 igual que Color
+pub fn apply_diffuse_hdr(base: Color, fragment: &Fragment, light: &Light) -> [f32; 3] {
+  let (base, tint) = (base.to_float(), light.color.to_float());
+  let intensity = diffuse_intensity(fragment, light);
+  [0, 1, 2].map(|i| (base[i] * tint[i] * intensity).min(1.0))
+}
+
+fn diffuse_intensity(fragment: &Fragment, light: &Light) -> f32 {
+  let light_dir = (light.position - fragment.world_position).normalize();
+  let diffuse = fragment.normal.dot(&light_dir).max(0.0);
+  (diffuse * light.intensity).max(AMBIENT_INTENSITY)
 }
 
-fn sun_shader(fragment: &Fragment, uniforms: &Uniforms, _light: &Light) -> Color {
-  let base_color = Color::new(255, 200, 50);      // Color cálido base
-  let highlight_color = Color::new(255, 255, 150); // Color de alta intensidad
+pub fn sun_shader(fragment: &Fragment, uniforms: &Uniforms, _light: &Light) -> Color {
+  // Interpolación entre colores para simular la variación en la superficie
+  let (base_color, highlight_color, noise_value) = sun_surface(fragment, uniforms);
+  base_color.lerp(&highlight_color, noise_value)
+}
+
+// Los dos colores del sol y cuánto se acerca cada fragmento al segundo
+fn sun_surface(fragment: &Fragment, uniforms: &Uniforms) -> (Color, Color, f32) {
+  let base_color = color_param(uniforms, &SUN_BASE);           // Color cálido base
+  let highlight_color = color_param(uniforms, &SUN_HIGHLIGHT); // Color de alta intensidad
 
   // Efecto de pulsación en la superficie
-  let pulsate = ((uniforms.time as f32 * 0.05).sin() * 0.5 + 0.5) * 0.3;
+  let pulsate = ((uniforms.time * 3.0).sin() * 0.5 + 0.5) * 0.3;
   let zoom = 50.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = uniforms.noise_for(SUN_SHADER).get_noise_2d(
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
   ) + pulsate;
 
-  // Interpolación entre colores para simular la variación en la superficie
-  base_color.lerp(&highlight_color, noise_value)
+  (base_color, highlight_color, noise_value)
+}
+
+pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(earth_surface(fragment, uniforms), fragment, &uniforms.light)
 }
 
-fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+fn earth_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   // Colores para diferentes biomas
-  let land_color = Color::new(34, 139, 34);       // Verde para continentes
-  let ocean_color = Color::new(30, 144, 255);     // Azul para océanos
-  let snow_color = Color::new(255, 250, 250);     // Blanco para zonas polares
-  let cloud_color = Color::new(255, 255, 255);    // Blanco para las nubes
+  let land_color = color_param(uniforms, &EARTH_LAND);        // Verde para continentes
+  let ocean_color = color_param(uniforms, &EARTH_OCEAN);      // Azul para océanos
+  let deep_ocean_color = color_param(uniforms, &EARTH_DEEP_OCEAN);
+  let snow_color = color_param(uniforms, &EARTH_SNOW);        // Blanco para zonas polares
+  let cloud_color = color_param(uniforms, &EARTH_CLOUD);      // Blanco para las nubes
 
   // Zoom para el ruido que genera los biomas
-  let zoom = 15.0;
-  let noise_value = uniforms.noise.get_noise_3d(
+  let zoom = param(uniforms, &EARTH_ZOOM);
+  let noise_value = smooth_noise_3d(
+      uniforms,
+      uniforms.noise_for(EARTH_SHADER),
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
       fragment.vertex_position.z * zoom,
   );
 
   // Capa base para la superficie terrestre
-  let base_color = if noise_value < -0.3 {
-      ocean_color.lerp(&Color::new(25, 105, 210), (noise_value + 0.3) / 0.3)
-  } else if noise_value > 0.7 {
-      land_color.lerp(&snow_color, (noise_value - 0.7) / 0.3)
+  let ocean_threshold = param(uniforms, &EARTH_OCEAN_THRESHOLD);
+  let base_color = if noise_value < ocean_threshold {
+      ocean_color.lerp(&deep_ocean_color, (noise_value - ocean_threshold) / 0.3)
   } else {
-      ocean_color.lerp(&land_color, (noise_value + 0.3) / 1.0)
+      ocean_color.lerp(&land_color, ((noise_value - ocean_threshold) / 1.0).min(1.0))
   };
 
-  // Primera capa de nubes en movimiento
-  let cloud_zoom1 = 10.0;
-  let displacement_x1 = uniforms.noise.get_noise_2d(fragment.vertex_position.x * cloud_zoom1, fragment.vertex_position.y * cloud_zoom1) * 0.3;
-  let displacement_z1 = uniforms.noise.get_noise_2d(fragment.vertex_position.z * cloud_zoom1, fragment.vertex_position.y * cloud_zoom1) * 0.3;
-  let cloud_noise_value1 = uniforms.noise.get_noise_3d(
-      fragment.vertex_position.x * cloud_zoom1 + displacement_x1,
-      fragment.vertex_position.y * cloud_zoom1,
-      fragment.vertex_position.z * cloud_zoom1 + displacement_z1,
-  );
+  // Casquetes polares: la latitud decide dónde hay nieve y el ruido
+  // deforma el borde para que no quede como una línea recta
+  let latitude = fragment.vertex_position.try_normalize(f32::EPSILON).map_or(0.0, |p| p.y.abs());
+  let edge = latitude + noise_value * 0.15;
+  let snow = ((edge - uniforms.polar_extent) / 0.1).clamp(0.0, 1.0);
+  let base_color = base_color.lerp(&snow_color, snow);
+  let base_color = with_albedo(base_color, fragment, uniforms, "earth", param(uniforms, &EARTH_ALBEDO_DETAIL));
 
-  // Opacidad de la primera capa de nubes
-  let cloud_opacity1 = (cloud_noise_value1 * 0.5 + 0.5).min(1.0).max(0.0);
+  // Primera capa de nubes en movimiento
+  let cloud_opacity1 = cloud_opacity(uniforms, &fragment.vertex_position, 10.0, 0.3);
 
   // Segunda capa de nubes en movimiento (opcional, para mayor complejidad)
-  let cloud_zoom2 = 8.0;
-  let displacement_x2 = uniforms.noise.get_noise_2d(fragment.vertex_position.x * cloud_zoom2, fragment.vertex_position.y * cloud_zoom2) * 0.4;
-  let displacement_z2 = uniforms.noise.get_noise_2d(fragment.vertex_position.z * cloud_zoom2, fragment.vertex_position.y * cloud_zoom2) * 0.4;
-  let cloud_noise_value2 = uniforms.noise.get_noise_3d(
-      fragment.vertex_position.x * cloud_zoom2 + displacement_x2,
-      fragment.vertex_position.y * cloud_zoom2,
-      fragment.vertex_position.z * cloud_zoom2 + displacement_z2,
-  );
-
-  // Opacidad de la segunda capa de nubes
-  let cloud_opacity2 = (cloud_noise_value2 * 0.5 + 0.5).min(1.0).max(0.0);
+  let cloud_opacity2 = cloud_opacity(uniforms, &fragment.vertex_position, 8.0, 0.4);
+
+  // Sombra de las nubes: se muestrea la nube desplazada hacia la luz. La
+  // dirección se arma en el mundo y se lleva al espacio de objeto, donde
+  // viven las nubes; el modelo solo gira y escala por igual, así que su
+  // transpuesta deshace el giro
+  let world_light_dir = uniforms.light.position - fragment.world_position;
+  let light_dir = (mat4_to_mat3(&uniforms.model_matrix).transpose() * world_light_dir)
+      .try_normalize(f32::EPSILON)
+      .unwrap_or_else(Vec3::zeros);
+  let shadow_position = fragment.vertex_position + light_dir * 0.3;
+  let shadow = (cloud_opacity(uniforms, &shadow_position, 10.0, 0.3)
+      + cloud_opacity(uniforms, &shadow_position, 8.0, 0.4)) * 0.5;
+  let base_color = base_color * (1.0 - shadow * 0.4);
 
   // Combinación de las capas de nubes con la superficie
   let combined_clouds = cloud_color * cloud_opacity1 + cloud_color * cloud_opacity2;
@@ -167,124 +424,328 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   final_color
 }
 
+// Si hay una textura cargada con ese nombre, la muestrea en las coordenadas
+// de textura del fragmento y deja `detail` del color procedural encima
+fn with_albedo(procedural: Color, fragment: &Fragment, uniforms: &Uniforms, name: &str, detail: f32) -> Color {
+  match uniforms.textures.get(name) {
+    Some(texture) => texture
+      .sample(fragment.tex_coords.x, fragment.tex_coords.y)
+      .lerp(&procedural, detail),
+    None => procedural,
+  }
+}
+
+// Desplazamientos fijos (en espacio de ruido) para suavizar el muestreo
+const NOISE_JITTER: [(f32, f32, f32); 8] = [
+  ( 0.0,  0.0,  0.0),
+  ( 0.5,  0.5, -0.5),
+  (-0.5,  0.5,  0.5),
+  ( 0.5, -0.5,  0.5),
+  (-0.5, -0.5, -0.5),
+  ( 0.7,  0.0,  0.0),
+  ( 0.0, -0.7,  0.0),
+  ( 0.0,  0.0,  0.7),
+];
+
+// Promedia `uniforms.noise_samples` muestras cercanas para bordes más suaves
+fn smooth_noise_3d(uniforms: &Uniforms, noise: &FastNoiseLite, x: f32, y: f32, z: f32) -> f32 {
+  let samples = uniforms.noise_samples.clamp(1, NOISE_JITTER.len());
+  let sum: f32 = NOISE_JITTER[..samples].iter()
+    .map(|(dx, dy, dz)| noise.get_noise_3d(x + dx, y + dy, z + dz))
+    .sum();
+  sum / samples as f32
+}
 
-fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  // Colores base para Marte
-  let base_color = Color::new(139, 69, 19);       // Marrón oscuro para la base
-  let crater_color = Color::new(105, 54, 30);     // Color más oscuro para los cráteres
-  let rocky_color = Color::new(169, 86, 30);      // Color intermedio para áreas rocosas
+// Oscurece el fondo de los cráteres de `uniforms.craters` y aclara su borde;
+// `position` es la posición en espacio de objeto, solo importa su dirección
+fn with_craters(surface: Color, floor_color: Color, position: &Vec3, uniforms: &Uniforms) -> Color {
+  let direction = match position.try_normalize(f32::EPSILON) {
+    Some(direction) => direction,
+    None => return surface,
+  };
+
+  let (floor, rim) = crater_profile(&uniforms.craters, &direction);
+  surface.lerp(&floor_color, floor * 0.8) * (1.0 + rim * 0.2)
+}
+
+// Paso (en coordenadas de ruido) de las diferencias finitas de rim_detail
+const SLOPE_STEP: f32 = 0.5;
+
+// Pendiente (gradiente tangencial del ruido por unidad de frecuencia) a partir
+// de la cual aparece el detalle, y a partir de la cual es completo
+const SLOPE_DETAIL_START: f32 = 2.5;
+const SLOPE_DETAIL_FULL: f32 = 4.0;
+
+// Frecuencia del detalle relativa a la del relieve
+const DETAIL_ZOOM_FACTOR: f32 = 6.0;
+
+// Factor de color (alrededor de 1) con textura rocosa de alta frecuencia donde
+// el relieve `noise(position * zoom)` es empinado; en zonas planas vale 1. El
+// gradiente se estima con tres muestras extra y se descarta su componente
+// radial, que no es pendiente sobre la superficie.
+fn rim_detail(noise: &FastNoiseLite, position: &Vec3, zoom: f32, strength: f32) -> f32 {
+  if strength <= 0.0 {
+    return 1.0;
+  }
 
-  // Capa base para la superficie rocosa
+  let p = position * zoom;
+  let height = noise.get_noise_3d(p.x, p.y, p.z);
+  let gradient = Vec3::new(
+      noise.get_noise_3d(p.x + SLOPE_STEP, p.y, p.z) - height,
+      noise.get_noise_3d(p.x, p.y + SLOPE_STEP, p.z) - height,
+      noise.get_noise_3d(p.x, p.y, p.z + SLOPE_STEP) - height,
+  ) / SLOPE_STEP;
+
+  let up = position.try_normalize(f32::EPSILON).unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+  let tangential = gradient - up * gradient.dot(&up);
+  let slope = tangential.magnitude() / noise.frequency;
+  let x = ((slope - SLOPE_DETAIL_START) / (SLOPE_DETAIL_FULL - SLOPE_DETAIL_START)).clamp(0.0, 1.0);
+  let steepness = x * x * (3.0 - 2.0 * x);
+  if steepness <= 0.0 {
+    return 1.0;
+  }
+
+  let d = p * DETAIL_ZOOM_FACTOR;
+  let detail = noise.get_noise_3d(d.x + 101.0, d.y, d.z - 101.0);
+  1.0 + detail * strength * steepness
+}
+
+// Opacidad de una capa de nubes; `earth.cloud_detail` mezcla el ruido
+// compartido de una octava con el ruido fractal propio de las nubes
+fn cloud_opacity(uniforms: &Uniforms, position: &Vec3, zoom: f32, displacement: f32) -> f32 {
+  let detail = param(uniforms, &EARTH_CLOUD_DETAIL).clamp(0.0, 1.0);
+  if detail <= 0.0 {
+    cloud_layer(&uniforms.noise, position, zoom, displacement)
+  } else if detail >= 1.0 {
+    cloud_layer(&uniforms.cloud_noise, position, zoom, displacement)
+  } else {
+    let simple = cloud_layer(&uniforms.noise, position, zoom, displacement);
+    let fractal = cloud_layer(&uniforms.cloud_noise, position, zoom, displacement);
+    simple + (fractal - simple) * detail
+  }
+}
+
+// Capa de nubes con desplazamiento de dominio
+fn cloud_layer(noise: &FastNoiseLite, position: &Vec3, zoom: f32, displacement: f32) -> f32 {
+  let displacement_x = noise.get_noise_2d(position.x * zoom, position.y * zoom) * displacement;
+  let displacement_z = noise.get_noise_2d(position.z * zoom, position.y * zoom) * displacement;
+  let cloud_noise_value = noise.get_noise_3d(
+      position.x * zoom + displacement_x,
+      position.y * zoom,
+      position.z * zoom + displacement_z,
+  );
+
+  (cloud_noise_value * 0.5 + 0.5).clamp(0.0, 1.0)
+}
+
+pub fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(mars_surface(fragment, uniforms), fragment, &uniforms.light)
+}
+
+fn mars_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  // Paleta de Marte: del basalto oscuro a las tierras altas oxidadas
+  let palette = Gradient::new(vec![
+      (0.0, color_param(uniforms, &MARS_BASALT)),      // Basalto oscuro
+      (0.35, color_param(uniforms, &MARS_BASE)),       // Marrón oscuro para la base
+      (0.7, color_param(uniforms, &MARS_ROCK)),        // Color intermedio para áreas rocosas
+      (1.0, color_param(uniforms, &MARS_HIGHLANDS)),   // Tierras altas oxidadas
+  ]);
+  let crater_color = color_param(uniforms, &MARS_CRATER);  // Color más oscuro para los cráteres
+
+  // Capa base para la superficie rocosa; el ruido 3D sobre la posición
+  // completa no deja costuras ni se estira en los polos
   let zoom = 20.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = uniforms.noise_for(MARS_SHADER).get_noise_3d(
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
+      fragment.vertex_position.z * zoom,
   );
 
   // Interpolación para dar aspecto rocoso
-  let base_layer = base_color.lerp(&rocky_color, noise_value * 0.5 + 0.5);
+  let base_layer = palette.sample(noise_value * 0.5 + 0.5);
+  let base_layer = with_albedo(base_layer, fragment, uniforms, "mars", param(uniforms, &MARS_ALBEDO_DETAIL));
 
-  // Añadir detalles de cráteres
-  let crater_zoom = 8.0;
-  let crater_noise_value = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * crater_zoom,
-      fragment.vertex_position.y * crater_zoom,
-  );
+  // Cráteres redondos de la lista compartida; Marte la lee reflejada para no
+  // repetir la misma distribución que Mercurio
+  let color = with_craters(base_layer, crater_color, &-fragment.vertex_position, uniforms);
 
-  // Interpolación para los cráteres
-  if crater_noise_value < -0.3 {
-      base_layer.lerp(&crater_color, (-crater_noise_value - 0.3) / 0.7)
-  } else {
-      base_layer
-  }
+  // Textura rocosa en las pendientes del relieve
+  let relief_zoom = 8.0;
+  let detail = rim_detail(uniforms.noise_for(MARS_SHADER), &fragment.vertex_position, relief_zoom, param(uniforms, &MARS_RIM_DETAIL));
+
+  color * detail
 }
 
 
 
 
-fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let planet_color = Color::new(255, 225, 180);  // Color suave para Saturno
-  let ring_color = Color::new(220, 220, 220);    // Color gris para los anillos
+// Solo el cuerpo del planeta; los anillos son una malla aparte, ver ring_shader
+pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(saturn_surface(fragment, uniforms), fragment, &uniforms.light)
+}
 
-  let distance_from_center = fragment.vertex_position.x.hypot(fragment.vertex_position.y);
-  let ring_width = 5.0;
-  let ring_threshold = 10.0;
+fn saturn_surface(_fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  color_param(uniforms, &SATURN_PLANET)  // Color suave para Saturno
+}
 
-  // Determina si el fragmento está dentro de los anillos
-  let in_rings = distance_from_center > ring_threshold && (distance_from_center % ring_width) < 1.0;
+// Anillos planos (Obj::annulus): `tex_coords.y` va del borde interior (0)
+// al exterior (1). Bandas finas de ruido a lo largo del radio, el anillo C
+// interior más tenue, la división de Cassini casi vacía y los bordes que se
+// desvanecen. La luz usa la normal del plano; la sombra del planeta llega
+// por eclipse_light, ya que render_rings lo agrega como ocultador.
+pub fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+  let (color, alpha) = ring_surface(fragment, uniforms);
+  (apply_diffuse(color, fragment, &uniforms.light), alpha)
+}
 
-  if in_rings {
-      ring_color
-  } else {
-      planet_color
-  }
+fn ring_surface(fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+  let ring_color = color_param(uniforms, &SATURN_RING);
+  let radial = fragment.tex_coords.y.clamp(0.0, 1.0);
+
+  let bands = uniforms.noise_for(RING_SHADER).get_noise_2d(radial * 300.0, 0.0) * 0.5 + 0.5;
+  let density = if radial < 0.25 { 0.35 } else { 0.85 };
+  let cassini = 1.0 - (1.0 - ((radial - 0.72) / 0.03).abs()).clamp(0.0, 1.0) * 0.9;
+  let smooth_edge = (radial / 0.04).min((1.0 - radial) / 0.04).clamp(0.0, 1.0);
+  let alpha = (density * (0.6 + 0.4 * bands) * cassini * smooth_edge).clamp(0.0, 1.0);
+
+  (ring_color * (0.75 + 0.25 * bands), alpha)
 }
 
 
-fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let band_color1 = Color::new(205, 133, 63);    // Color para bandas marrones
-  let band_color2 = Color::new(255, 222, 173);   // Color para bandas claras
-  let storm_color = Color::new(255, 69, 0);      // Rojo para la gran mancha roja
+// Gran mancha roja: latitud y longitud (radianes) de su centro en el
+// planeta, y sus semiejes (longitud, latitud) como un óvalo
+const JUPITER_SPOT_LATITUDE: f32 = -0.38;
+const JUPITER_SPOT_LONGITUDE: f32 = 1.2;
+const JUPITER_SPOT_SIZE: (f32, f32) = (0.32, 0.14);
 
-  let zoom = 10.0;
-  let y_pos = fragment.vertex_position.y * zoom;
+// Radianes por segundo que derivan las nubes respecto a la superficie; el
+// sentido alterna de una banda a la siguiente
+const JUPITER_WIND_SPEED: f32 = 0.03;
 
-  let band_factor = (y_pos.sin() * 0.5 + 0.5);   // Variación sinusoidal para bandas
+// Bandas de latitud: la altura `y` de la dirección decide la franja, clara u
+// oscura, y el ruido turbulento (estirado a lo largo de los paralelos)
+// empuja los bordes para que se arremolinen. Cada franja arrastra su
+// turbulencia con el tiempo, en sentidos opuestos como los vientos zonales.
+pub fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(jupiter_surface(fragment, uniforms), fragment, &uniforms.light)
+}
 
-  // Simular la gran mancha roja en una ubicación específica
-  if fragment.vertex_position.x.abs() < 0.3 && fragment.vertex_position.y > 0.5 {
-      storm_color
-  } else if band_factor > 0.5 {
-      band_color1
-  } else {
-      band_color2
-  }
+fn jupiter_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let band_color1 = color_param(uniforms, &JUPITER_DARK_BAND);   // Color para bandas marrones
+  let band_color2 = color_param(uniforms, &JUPITER_LIGHT_BAND);  // Color para bandas claras
+  let storm_color = color_param(uniforms, &JUPITER_STORM);       // Rojo para la gran mancha roja
+
+  let direction = fragment.vertex_position.try_normalize(f32::EPSILON).unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+  let latitude = direction.y.clamp(-1.0, 1.0).asin();
+  let longitude = direction.z.atan2(direction.x);
+
+  let zoom = param(uniforms, &JUPITER_BAND_ZOOM);
+  let band_phase = direction.y * zoom;
+
+  // Turbulencia desplazada en longitud según el viento de su banda
+  let wind = uniforms.time * JUPITER_WIND_SPEED * band_phase.cos();
+  let (sin_lon, cos_lon) = (longitude + wind).sin_cos();
+  let p = Vec3::new(cos_lon * latitude.cos(), direction.y * 4.0, sin_lon * latitude.cos()) * 150.0;
+  let turbulence = uniforms.noise_for(JUPITER_SHADER).get_noise_3d(p.x, p.y, p.z);
+
+  let band_factor = (band_phase + turbulence * param(uniforms, &JUPITER_TURBULENCE)).sin() * 0.5 + 0.5;
+  let x = ((band_factor - 0.3) / 0.4).clamp(0.0, 1.0);
+  let color = band_color2.lerp(&band_color1, x * x * (3.0 - 2.0 * x));
+
+  // La mancha es un óvalo fijo en el planeta con el borde deformado por la
+  // misma turbulencia
+  let delta_longitude = (longitude - JUPITER_SPOT_LONGITUDE + PI).rem_euclid(2.0 * PI) - PI;
+  let spot_distance = ((delta_longitude * latitude.cos() / JUPITER_SPOT_SIZE.0).powi(2)
+      + ((latitude - JUPITER_SPOT_LATITUDE) / JUPITER_SPOT_SIZE.1).powi(2)).sqrt()
+      + turbulence * 0.15;
+  let spot = (1.0 - (spot_distance - 0.8) / 0.2).clamp(0.0, 1.0);
+  color.lerp(&storm_color, spot * 0.85)
 }
 
 
-fn mercury_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let base_color = Color::new(169, 169, 169);  // Gris claro
-  let crater_color = Color::new(105, 105, 105);  // Gris oscuro para cráteres
+pub fn mercury_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(mercury_surface(fragment, uniforms), fragment, &uniforms.light)
+}
+
+fn mercury_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let base_color = color_param(uniforms, &MERCURY_BASE);      // Gris claro
+  let crater_color = color_param(uniforms, &MERCURY_CRATER);  // Gris oscuro para cráteres
 
   let zoom = 20.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = uniforms.noise_for(MERCURY_SHADER).get_noise_3d(
       fragment.vertex_position.x * zoom,
-      fragment.vertex_position.y * zoom
+      fragment.vertex_position.y * zoom,
+      fragment.vertex_position.z * zoom,
   );
 
-  // Simular cráteres basados en el ruido
-  let is_crater = noise_value < -0.2;
-
-  let color = if is_crater {
-      crater_color
+  // Llanuras lisas más oscuras donde el ruido es bajo
+  let plains = noise_value < param(uniforms, &MERCURY_PLAINS_THRESHOLD);
+  let surface = if plains {
+      base_color.lerp(&crater_color, 0.5)
   } else {
       base_color
   };
 
-  color * fragment.intensity
+  // Cráteres redondos con fondo oscuro y borde claro
+  let color = with_craters(surface, crater_color, &fragment.vertex_position, uniforms);
+
+  // Textura rocosa en las pendientes del relieve
+  let detail = rim_detail(uniforms.noise_for(MERCURY_SHADER), &fragment.vertex_position, zoom, param(uniforms, &MERCURY_RIM_DETAIL));
+
+  color * detail
+}
+
+pub fn venus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(venus_surface(fragment, uniforms), fragment, &uniforms.light)
 }
 
-fn venus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let base_color = Color::new(218, 165, 32);     // Color cálido para la superficie
-  let cloud_color = Color::new(255, 228, 181);   // Color crema para las nubes
+fn venus_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let palette = Gradient::new(vec![
+      (0.0, color_param(uniforms, &VENUS_SURFACE)),    // Color cálido para la superficie
+      (0.5, color_param(uniforms, &VENUS_HAZE)),       // Bruma amarillenta
+      (1.0, color_param(uniforms, &VENUS_CLOUD_TOP)),  // Color crema para las nubes
+  ]);
 
+  let noise = uniforms.noise_for(VENUS_SHADER);
   let zoom = 8.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = noise.get_noise_3d(
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
+      fragment.vertex_position.z * zoom,
+  );
+  let surface = palette.sample(noise_value.abs());
+
+  // Capa densa de nubes de ácido sulfúrico: el ruido se deforma con otra
+  // muestra de ruido (domain warping) y se desplaza con el tiempo
+  let p = fragment.vertex_position * 12.0;
+  let drift = uniforms.time * 12.0;
+  let warp = noise.get_noise_3d(p.x * 2.0 + drift, p.y * 2.0, p.z * 2.0);
+  let swirl = noise.get_noise_3d(
+      p.x * 3.0 + warp * 40.0 - drift,
+      p.y * 6.0 + warp * 20.0,
+      p.z * 3.0 - warp * 40.0,
   );
 
-  base_color.lerp(&cloud_color, noise_value.abs())
+  let deck_dark = color_param(uniforms, &VENUS_DECK_DARK);
+  let deck_light = color_param(uniforms, &VENUS_DECK_LIGHT);
+  let cloud_deck = deck_dark.lerp(&deck_light, swirl * 0.5 + 0.5);
+
+  // Con la opacidad por defecto apenas se adivina la superficie
+  surface.lerp(&cloud_deck, param(uniforms, &VENUS_CLOUD_OPACITY))
 }
 
 
 
-fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let base_color = Color::new(173, 216, 230);  // Azul claro
-  let highlight_color = Color::new(224, 255, 255);  // Azul verdoso
+pub fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(uranus_surface(fragment, uniforms), fragment, &uniforms.light)
+}
+
+fn uranus_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let base_color = color_param(uniforms, &URANUS_BASE);            // Azul claro
+  let highlight_color = color_param(uniforms, &URANUS_HIGHLIGHT);  // Azul verdoso
 
   let zoom = 5.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = uniforms.noise_for(URANUS_SHADER).get_noise_2d(
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
   );
@@ -292,12 +753,16 @@ fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   base_color.lerp(&highlight_color, noise_value)
 }
 
-fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let base_color = Color::new(0, 0, 128);      // Azul profundo
-  let highlight_color = Color::new(70, 130, 180); // Azul cielo
+pub fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(neptune_surface(fragment, uniforms), fragment, &uniforms.light)
+}
+
+fn neptune_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let base_color = color_param(uniforms, &NEPTUNE_BASE);            // Azul profundo
+  let highlight_color = color_param(uniforms, &NEPTUNE_HIGHLIGHT);  // Azul cielo
 
   let zoom = 5.0;
-  let noise_value = uniforms.noise.get_noise_2d(
+  let noise_value = uniforms.noise_for(NEPTUNE_SHADER).get_noise_2d(
       fragment.vertex_position.x * zoom,
       fragment.vertex_position.y * zoom,
   );
@@ -305,3 +770,110 @@ fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   base_color.lerp(&highlight_color, noise_value)
 }
 
+pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  apply_diffuse(moon_surface(fragment, uniforms), fragment, &uniforms.light)
+}
+
+fn moon_surface(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let highlands_color = color_param(uniforms, &MOON_HIGHLANDS);  // Gris claro de las tierras altas
+  let mare_color = color_param(uniforms, &MOON_MARE);            // Basalto oscuro de los mares
+  let crater_color = color_param(uniforms, &MOON_CRATER);        // Fondo de los cráteres
+
+  // Mares: manchas grandes de basalto con el borde difuminado
+  let zoom = 15.0;
+  let noise_value = uniforms.noise_for(MOON_SHADER).get_noise_3d(
+      fragment.vertex_position.x * zoom,
+      fragment.vertex_position.y * zoom,
+      fragment.vertex_position.z * zoom,
+  );
+  let threshold = param(uniforms, &MOON_MARE_THRESHOLD);
+  let mare = ((noise_value - threshold) / 0.1).clamp(0.0, 1.0);
+  let surface = highlands_color.lerp(&mare_color, mare);
+
+  // Los mismos cráteres que Mercurio, girados un cuarto de vuelta para que
+  // la Luna no muestre la misma cara
+  let p = fragment.vertex_position;
+  with_craters(surface, crater_color, &Vec3::new(-p.z, p.y, p.x), uniforms)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nalgebra_glm::{Mat4, Vec2, rotation, scaling};
+  use crate::uniforms::{create_perspective_matrix, create_viewport_matrix};
+
+  fn test_uniforms() -> Uniforms {
+    Uniforms::new(create_perspective_matrix(800.0, 600.0), create_viewport_matrix(800.0, 600.0))
+  }
+
+  // Fragmento blanco en `position` (de objeto y de mundo) con la normal dada
+  fn fragment_at(position: Vec3, normal: Vec3) -> Fragment {
+    let tangent = normal.cross(&Vec3::new(0.0, 1.0, 0.0)).try_normalize(f32::EPSILON).unwrap_or(Vec3::new(1.0, 0.0, 0.0));
+    Fragment::new(Vec2::new(0.0, 0.0), Color::new(255, 255, 255), 0.5, normal, tangent, Vec2::new(0.0, 0.0), position, position)
+  }
+
+  #[test]
+  fn normal_stays_perpendicular_under_non_uniform_scale() {
+    let mut uniforms = test_uniforms();
+    uniforms.displacement = 0.0;
+    uniforms.model_matrix = rotation(0.3, &Vec3::new(0.0, 0.0, 1.0)) * scaling(&Vec3::new(2.0, 1.0, 1.0));
+
+    // Punto de la esfera unitaria con su normal y una tangente en el plano XY
+    let position = Vec3::new(1.0, 1.0, 0.0).normalize();
+    let vertex = Vertex::new(position, position, Vec2::new(0.0, 0.0));
+    let transformed = vertex_shader(&vertex, &uniforms);
+
+    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
+    let tangent = model_mat3 * Vec3::new(-1.0, 1.0, 0.0);
+    let world_position = model_mat3 * position;
+
+    let normal = transformed.transformed_normal;
+    assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+    assert!(normal.dot(&tangent).abs() < 1e-5);
+    assert!(normal.dot(&world_position) > 0.0);
+    // Un plain model * normal no sería perpendicular
+    assert!((model_mat3 * position).normalize().dot(&tangent).abs() > 0.1);
+  }
+
+  #[test]
+  fn singular_model_still_gives_a_finite_normal() {
+    let model_matrix = scaling(&Vec3::new(1.0, 0.0, 1.0));
+    let normal = create_normal_matrix(&model_matrix) * Vec3::new(1.0, 0.0, 0.0);
+    assert!(normal.iter().all(|c| c.is_finite()));
+    assert_eq!(create_normal_matrix(&Mat4::identity()), mat4_to_mat3(&Mat4::identity()));
+  }
+
+  #[test]
+  fn mars_is_brighter_facing_the_light() {
+    let uniforms = test_uniforms();
+    // Misma posición sobre la esfera, solo cambia hacia dónde mira la normal
+    let toward_light = uniforms.light.position.normalize();
+    let brightness = |color: Color| color.to_float().iter().sum::<f32>();
+
+    let lit = mars_shader(&fragment_at(toward_light, toward_light), &uniforms);
+    let unlit = mars_shader(&fragment_at(toward_light, -toward_light), &uniforms);
+    assert!(brightness(lit) > brightness(unlit) * 1.5, "lit {} vs unlit {}", lit, unlit);
+  }
+
+  #[test]
+  fn jupiter_bands_alternate() {
+    let mut uniforms = test_uniforms();
+    // Bandas rectas, para que solo cuente la latitud
+    uniforms.params.insert(JUPITER_TURBULENCE.name.to_string(), 0.0);
+    let zoom = JUPITER_BAND_ZOOM.default;
+    let toward_light = uniforms.light.position.normalize();
+
+    // Lejos de la mancha, en fases de banda separadas por medio periodo
+    let longitude = JUPITER_SPOT_LONGITUDE + PI;
+    let sample = |phase: f32| {
+      let y = phase / zoom;
+      let ring = (1.0 - y * y).sqrt();
+      let position = Vec3::new(longitude.cos() * ring, y, longitude.sin() * ring);
+      jupiter_shader(&fragment_at(position, toward_light), &uniforms).to_hex()
+    };
+
+    let (dark, light, dark_again) = (sample(PI / 2.0), sample(1.5 * PI), sample(2.5 * PI));
+    assert_ne!(dark, light);
+    assert_eq!(dark, dark_again);
+  }
+}
@@ -0,0 +1,117 @@
+// skybox.rs
+
+use std::io;
+use std::path::Path;
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// File names of the six cube faces, in +X, -X, +Y, -Y, +Z, -Z order
+const FACE_NAMES: [&str; 6] = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+
+struct Face {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Face {
+    fn load(path: &Path) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?.to_rgb8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| Color::new(pixel[0], pixel[1], pixel[2]))
+            .collect();
+
+        Ok(Face {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+        })
+    }
+
+    // Nearest texel for u, v in [0, 1]
+    fn sample(&self, u: f32, v: f32) -> Color {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
+
+// Cubemap backdrop sampled by view direction
+pub struct Skybox {
+    faces: Vec<Face>,
+}
+
+impl Skybox {
+    // Loads px/nx/py/ny/pz/nz.png from the given directory
+    pub fn load(directory: &str) -> io::Result<Self> {
+        let directory = Path::new(directory);
+        let faces = FACE_NAMES
+            .iter()
+            .map(|name| Face::load(&directory.join(name)))
+            .collect::<io::Result<Vec<Face>>>()?;
+
+        if faces.iter().any(|face| face.width == 0 || face.height == 0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty skybox face"));
+        }
+
+        Ok(Skybox { faces })
+    }
+
+    // Picks the face along the dominant axis of `dir` and maps the other
+    // two components onto it (same layout as OpenGL cubemaps)
+    pub fn sample(&self, dir: Vec3) -> Color {
+        let (x, y, z) = (dir.x, dir.y, dir.z);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+        let (face, s, t, major) = if ax >= ay && ax >= az {
+            if x > 0.0 { (0, -z, -y, ax) } else { (1, z, -y, ax) }
+        } else if ay >= az {
+            if y > 0.0 { (2, x, z, ay) } else { (3, x, -z, ay) }
+        } else if z > 0.0 {
+            (4, x, -y, az)
+        } else {
+            (5, -x, -y, az)
+        };
+
+        if major == 0.0 {
+            return Color::black();
+        }
+
+        let u = (s / major + 1.0) * 0.5;
+        let v = (t / major + 1.0) * 0.5;
+        self.faces[face].sample(u, v)
+    }
+
+    // Fills every pixel nothing was drawn on with the skybox color seen
+    // through it, unprojecting the pixel with the inverse view-projection
+    pub fn draw(&self, framebuffer: &mut Framebuffer, view_matrix: &Mat4, projection_matrix: &Mat4) {
+        let inverse = match (projection_matrix * view_matrix).try_inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        let width = framebuffer.width;
+        let height = framebuffer.height;
+
+        for y in 0..height {
+            let ndc_y = 1.0 - 2.0 * (y as f32 + 0.5) / height as f32;
+            for x in 0..width {
+                let index = y * width + x;
+                if framebuffer.zbuffer[index] != f32::INFINITY {
+                    continue;
+                }
+
+                let ndc_x = 2.0 * (x as f32 + 0.5) / width as f32 - 1.0;
+                let near = inverse * Vec4::new(ndc_x, ndc_y, -1.0, 1.0);
+                let far = inverse * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
+                let direction = far.xyz() / far.w - near.xyz() / near.w;
+
+                framebuffer.write_pixel(index, self.sample(direction).to_hex());
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// starfield.rs
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+pub const DEFAULT_STAR_SEED: u64 = 1977;
+// Stars per steradian; the whole sky holds 4π times as many
+pub const DEFAULT_STAR_DENSITY: f32 = 400.0;
+
+// How far the dimmest star goes from the background towards its color
+const MIN_STAR_BRIGHTNESS: f32 = 0.4;
+// Blackbody range the star colors are drawn from, red dwarfs to blue giants
+const MIN_STAR_TEMPERATURE: f32 = 3000.0;
+const MAX_STAR_TEMPERATURE: f32 = 12000.0;
+
+struct Star {
+    direction: Vec3,
+    color: Color,
+    brightness: f32,
+}
+
+// Stars at infinity in fixed world directions. Only the camera rotation
+// moves them, so orbiting turns the sky the other way and zooming does not.
+pub struct Starfield {
+    stars: Vec<Star>,
+}
+
+impl Starfield {
+    // The same seed and density always give the same sky, so redrawing it
+    // every frame never shimmers. Directions are uniform over the sphere;
+    // brightness follows a quadratic curve, so faint stars outnumber bright ones.
+    pub fn new(seed: u64, density: f32) -> Self {
+        let count = (density.max(0.0) * 4.0 * std::f32::consts::PI) as usize;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let stars = (0..count).map(|_| {
+            let z: f32 = rng.gen_range(-1.0..1.0);
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            let ring = (1.0 - z * z).sqrt();
+            let direction = Vec3::new(ring * angle.cos(), z, ring * angle.sin());
+
+            let color = Color::from_temperature(rng.gen_range(MIN_STAR_TEMPERATURE..MAX_STAR_TEMPERATURE));
+            let shine: f32 = rng.gen();
+            let brightness = MIN_STAR_BRIGHTNESS + (1.0 - MIN_STAR_BRIGHTNESS) * shine * shine;
+            Star { direction, color, brightness }
+        }).collect();
+
+        Starfield { stars }
+    }
+
+    // Blends every star into the size x size pixel block it projects to,
+    // only where nothing was drawn; `size` is the supersampling factor, so a
+    // star keeps its brightness after the image is filtered down
+    pub fn draw(&self, framebuffer: &mut Framebuffer, view_matrix: &Mat4, projection_matrix: &Mat4, size: usize) {
+        let view_projection = projection_matrix * view_matrix;
+        let size = size.max(1);
+        let width = framebuffer.width / size;
+        let height = framebuffer.height / size;
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        for star in &self.stars {
+            // w = 0 drops the view translation: the star sits at infinity
+            let clip = view_projection * Vec4::new(star.direction.x, star.direction.y, star.direction.z, 0.0);
+            if clip.w <= 0.0 {
+                continue;
+            }
+
+            let ndc_x = clip.x / clip.w;
+            let ndc_y = clip.y / clip.w;
+            if ndc_x.abs() >= 1.0 || ndc_y.abs() >= 1.0 {
+                continue;
+            }
+
+            let x = (((ndc_x + 1.0) * 0.5 * width as f32) as usize).min(width - 1) * size;
+            let y = (((1.0 - ndc_y) * 0.5 * height as f32) as usize).min(height - 1) * size;
+            for row in y..y + size {
+                for column in x..x + size {
+                    let index = row * framebuffer.width + column;
+                    if framebuffer.zbuffer[index] != f32::INFINITY {
+                        continue;
+                    }
+
+                    let background = Color::from_hex(framebuffer.buffer[index]);
+                    let pixel = background.lerp(&star.color, star.brightness);
+                    framebuffer.write_pixel(index, pixel.to_hex());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniforms::{create_perspective_matrix, create_view_matrix};
+
+    // The sky seen from the origin looking down -Z, on a cleared framebuffer
+    fn draw_sky(framebuffer: &mut Framebuffer, starfield: &Starfield) {
+        let view = create_view_matrix(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let projection = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32);
+        framebuffer.clear();
+        starfield.draw(framebuffer, &view, &projection, 1);
+    }
+
+    #[test]
+    fn same_seed_draws_the_same_stars_every_clear() {
+        let background = Framebuffer::new(160, 120).buffer;
+        let mut framebuffer = Framebuffer::new(160, 120);
+        let starfield = Starfield::new(DEFAULT_STAR_SEED, DEFAULT_STAR_DENSITY);
+        draw_sky(&mut framebuffer, &starfield);
+        let first = framebuffer.buffer.clone();
+        assert!(first != background, "no stars drawn");
+
+        draw_sky(&mut framebuffer, &starfield);
+        assert!(framebuffer.buffer == first);
+        draw_sky(&mut framebuffer, &Starfield::new(DEFAULT_STAR_SEED, DEFAULT_STAR_DENSITY));
+        assert!(framebuffer.buffer == first);
+
+        draw_sky(&mut framebuffer, &Starfield::new(DEFAULT_STAR_SEED + 1, DEFAULT_STAR_DENSITY));
+        assert!(framebuffer.buffer != first);
+    }
+}
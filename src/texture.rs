@@ -0,0 +1,82 @@
+// texture.rs
+
+use std::io;
+
+use crate::color::Color;
+
+// Image held in memory for shaders to sample by texture coordinates
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Texture {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?.to_rgb8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| Color::new(pixel[0], pixel[1], pixel[2]))
+            .collect();
+
+        Texture::from_pixels(image.width() as usize, image.height() as usize, pixels)
+    }
+
+    // Row-major pixels, top row first; fails unless there are exactly
+    // width * height of them and neither side is zero
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> io::Result<Self> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "texture size does not match its pixels"));
+        }
+        Ok(Texture { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Bilinear sample between the four nearest texel centers. v = 0 is the
+    // top row, the same orientation Obj gives its texture coordinates. U wraps
+    // around, so a sphere's seam blends both edges; V clamps at the poles.
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        let x = u.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = v.clamp(0.0, 1.0) * self.height as f32 - 0.5;
+
+        let (x0, fx) = (x.floor(), x - x.floor());
+        let (y0, fy) = (y.floor(), y - y.floor());
+
+        let column = |offset: f32| (x0 + offset).rem_euclid(self.width as f32) as usize % self.width;
+        let row = |offset: f32| (y0 + offset).clamp(0.0, self.height as f32 - 1.0) as usize;
+        let texel = |column: usize, row: usize| self.pixels[row * self.width + column];
+
+        let (left, right) = (column(0.0), column(1.0));
+        let (top, bottom) = (row(0.0), row(1.0));
+        let upper = texel(left, top).lerp(&texel(right, top), fx);
+        let lower = texel(left, bottom).lerp(&texel(right, bottom), fx);
+        upper.lerp(&lower, fy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> Texture {
+        let (black, white) = (Color::black(), Color::new(255, 255, 255));
+        Texture::from_pixels(2, 2, vec![black, white, white, black]).unwrap()
+    }
+
+    #[test]
+    fn center_of_a_checker_is_the_average() {
+        let center = checker().sample(0.5, 0.5);
+        assert_eq!(center.to_hex(), Color::new(128, 128, 128).to_hex());
+
+        // Texel centers come back unfiltered
+        assert!(checker().sample(0.25, 0.25).is_black());
+        assert_eq!(checker().sample(0.75, 0.25).to_hex(), 0xFFFFFF);
+    }
+}
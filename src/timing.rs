@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Rolling min/avg/max of the last `capacity` frame times
+pub struct FrameStats {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+    start: Option<Instant>,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        FrameStats {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            start: None,
+        }
+    }
+
+    pub fn begin(&mut self) {
+        self.start = Some(Instant::now());
+    }
+
+    // Closes the interval opened by `begin`; without one this does nothing
+    pub fn end(&mut self) {
+        if let Some(start) = self.start.take() {
+            self.record(start.elapsed());
+        }
+    }
+
+    fn record(&mut self, duration: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    // True once per full window, so callers can report at a steady pace
+    pub fn window_complete(&self) -> bool {
+        self.samples.len() == self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.start = None;
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+}
+
+// Longest step one frame may advance the animation; after a stall (the
+// window being dragged, a breakpoint) the scene resumes instead of jumping
+pub const MAX_FRAME_STEP: f32 = 0.1;
+
+// Real seconds between consecutive frames, so the animation keeps the same
+// pace whatever the frame rate
+#[derive(Default)]
+pub struct FrameClock {
+    last: Option<Instant>,
+}
+
+impl FrameClock {
+    pub fn tick(&mut self) -> f32 {
+        self.tick_at(Instant::now())
+    }
+
+    // Seconds since the previous tick, 0 for the first one; never more than
+    // MAX_FRAME_STEP
+    pub fn tick_at(&mut self, now: Instant) -> f32 {
+        let elapsed = match self.last {
+            Some(last) => now.saturating_duration_since(last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last = Some(now);
+        elapsed.min(MAX_FRAME_STEP)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_keep_the_last_window() {
+        let mut stats = FrameStats::new(2);
+        stats.record(Duration::from_millis(10));
+        assert!(!stats.window_complete());
+        stats.record(Duration::from_millis(20));
+        stats.record(Duration::from_millis(30));
+
+        assert!(stats.window_complete());
+        assert_eq!(stats.min(), Duration::from_millis(20));
+        assert_eq!(stats.max(), Duration::from_millis(30));
+        assert_eq!(stats.average(), Duration::from_millis(25));
+
+        stats.clear();
+        assert_eq!(stats.average(), Duration::ZERO);
+    }
+
+    #[test]
+    fn frame_clock_accumulates_fractional_steps() {
+        let start = Instant::now();
+        let mut clock = FrameClock::default();
+        let mut time = clock.tick_at(start);
+        assert_eq!(time, 0.0);
+
+        // Two ordinary frames and a two-second stall, clamped to one step
+        for offset in [16, 49, 2049] {
+            time += clock.tick_at(start + Duration::from_millis(offset));
+        }
+        assert!((time - (0.016 + 0.033 + MAX_FRAME_STEP)).abs() < 1e-5, "accumulated {}", time);
+
+        // A clock that goes backwards does not rewind the animation
+        assert_eq!(clock.tick_at(start), 0.0);
+    }
+}
@@ -1,19 +1,60 @@
 use nalgebra_glm::{Vec3, dot, Vec2};
 use crate::fragment::Fragment;
-use crate::vertex::{self, Vertex};
+use crate::vertex::Vertex;
 use crate::color::Color;
+use crate::obj::any_perpendicular;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+// Screen-space area (in pixels, times two) below which a triangle is skipped
+const MIN_TRIANGLE_AREA: f32 = 1e-6;
+
+// A counter-clockwise triangle (seen from outside) ends up with a positive
+// screen-space area once the viewport flips Y (see create_viewport_matrix),
+// so a negative one faces away
+pub fn is_back_facing(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+  edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position) < 0.0
+}
+
+// Collects every fragment of the triangle inside a width x height target.
+// Convenient, but allocates; the render loop uses `for_each_fragment` instead.
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, width: usize, height: usize) -> Vec<Fragment> {
   let mut fragments = Vec::new();
+  for_each_fragment(v1, v2, v3, width, height, |fragment| fragments.push(fragment));
+  fragments
+}
+
+// Rasterizes the triangle and hands each covered pixel's fragment to `emit`
+// as soon as it is produced, without an intermediate buffer. Only pixels of
+// the width x height target are visited: a triangle grazing the near plane
+// can project millions of pixels off screen.
+pub fn for_each_fragment<F: FnMut(Fragment)>(
+  v1: &Vertex,
+  v2: &Vertex,
+  v3: &Vertex,
+  width: usize,
+  height: usize,
+  mut emit: F,
+) {
   let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+  let triangle_area = edge_function(&a, &b, &c);
 
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
+  // Zero-area (or NaN from a bad projection) triangles cover no pixels and
+  // would divide by zero in the barycentric coordinates
+  if triangle_area.is_nan() || triangle_area.abs() <= MIN_TRIANGLE_AREA {
+    return;
+  }
 
-  let triangle_area = edge_function(&a, &b, &c);
+  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+  let min_x = min_x.max(0);
+  let min_y = min_y.max(0);
+  let max_x = max_x.min(width as i32 - 1);
+  let max_y = max_y.min(height as i32 - 1);
 
-  // Iterate over each pixel in the bounding box
+  // Attributes are linear in 3D, not on screen: weighting by 1/w undoes the
+  // foreshortening of the perspective divide
+  let inverse_w = [1.0 / v1.clip_position.w, 1.0 / v2.clip_position.w, 1.0 / v3.clip_position.w];
+
+  // Iterate over each pixel in the bounding box, sampling at its center
   for y in min_y..=max_y {
     for x in min_x..=max_x {
       let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
@@ -22,39 +63,53 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
       let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
       // Check if the point is inside the triangle
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+      if (0.0..=1.0).contains(&w1) &&
+         (0.0..=1.0).contains(&w2) &&
+         (0.0..=1.0).contains(&w3) {
+
+        // Depth after the divide is already linear in screen space
+        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+        let (w1, w2, w3) = perspective_correct(w1, w2, w3, &inverse_w);
 
         // Interpolate normal
         let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
         let normal = normal.normalize();
 
-        // Calculate lighting intensity
-        let intensity = dot(&normal, &light_dir).max(0.0);
-
-        // Create a gray color (unchanged)
-        let color = Color::new(100, 100, 100); // Medium gray
+        // Interpolate tangent and keep it perpendicular to the normal; a
+        // tangent that is zero or parallel to the normal (opposing tangents
+        // across a seam, the default (1, 0, 0) on a +X normal) has nothing
+        // left after that, so any perpendicular stands in
+        let tangent = v1.transformed_tangent * w1 + v2.transformed_tangent * w2 + v3.transformed_tangent * w3;
+        let tangent = (tangent - normal * dot(&normal, &tangent))
+          .try_normalize(f32::EPSILON)
+          .unwrap_or_else(|| any_perpendicular(&normal));
 
-        // Interpolate depth
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+        // Interpolate the vertex color
+        let color = Color::interpolate(&v1.color, &v2.color, &v3.color, w1, w2, w3);
 
         // Positions of the original vertex
         let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
 
-        fragments.push(Fragment::new(
+        // World-space position for the lighting in the shaders, like the normal
+        let world_position = v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3;
+
+        // Texture coordinates
+        let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+
+        emit(Fragment::new(
             Vec2::new(x as f32, y as f32),
             color,
             depth,
             normal,
-            intensity,
+            tangent,
+            tex_coords,
+            world_position,
             vertex_position,
         ));
       }
     }
   }
-
-  fragments
 }
 
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
@@ -66,6 +121,18 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
+// Screen-space barycentric weights turned into weights of the triangle in
+// 3D; falls back to the screen-space ones if the w values are unusable
+fn perspective_correct(w1: f32, w2: f32, w3: f32, inverse_w: &[f32; 3]) -> (f32, f32, f32) {
+    let (p1, p2, p3) = (w1 * inverse_w[0], w2 * inverse_w[1], w3 * inverse_w[2]);
+    let sum = p1 + p2 + p3;
+    if sum > 0.0 && sum.is_finite() {
+        (p1 / sum, p2 / sum, p3 / sum)
+    } else {
+        (w1, w2, w3)
+    }
+}
+
 fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
     let w1 = edge_function(b, c, p) / area;
     let w2 = edge_function(c, a, p) / area;
@@ -76,4 +143,81 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A vertex already in screen space, as vertex_shader would leave it
+  fn screen_vertex(x: f32, y: f32, z: f32) -> Vertex {
+    Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0))
+  }
+
+  #[test]
+  fn colinear_vertices_give_no_fragments() {
+    let (a, b, c) = (screen_vertex(1.0, 1.0, 0.5), screen_vertex(5.0, 5.0, 0.5), screen_vertex(9.0, 9.0, 0.5));
+    assert!(triangle(&a, &b, &c, 16, 16).is_empty());
+
+    let point = screen_vertex(4.0, 4.0, 0.5);
+    assert!(triangle(&point, &point, &point, 16, 16).is_empty());
+
+    // The same check on a real triangle, so the empty result above is not a
+    // rasterizer that never emits anything
+    let c = screen_vertex(1.0, 9.0, 0.5);
+    assert!(!triangle(&a, &b, &c, 16, 16).is_empty());
+  }
+
+  #[test]
+  fn corner_colors_blend_to_gray_at_the_centroid() {
+    let mut a = screen_vertex(2.0, 2.0, 0.5);
+    let mut b = screen_vertex(30.0, 2.0, 0.5);
+    let mut c = screen_vertex(16.0, 30.0, 0.5);
+    a.color = Color::new(255, 0, 0);
+    b.color = Color::new(0, 255, 0);
+    c.color = Color::new(0, 0, 255);
+
+    // Centroid at (16, 11.33), the center of pixel (15, 10) is close enough
+    let fragments = triangle(&a, &b, &c, 32, 32);
+    let centroid = fragments.iter().find(|fragment| fragment.position == Vec2::new(15.0, 10.0)).unwrap();
+    for channel in centroid.color.to_float() {
+      assert!((0.25..0.42).contains(&channel), "expected gray near the centroid, got {}", centroid.color);
+    }
+  }
+
+  #[test]
+  fn centroid_gets_the_average_of_the_vertex_attributes() {
+    let mut corners = [(0.5, 0.5), (30.5, 0.5), (15.5, 30.5)].map(|(x, y)| screen_vertex(x, y, 0.5));
+    let normals = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+    let tex_coords = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.5, 1.0)];
+    for ((corner, normal), uv) in corners.iter_mut().zip(normals).zip(tex_coords) {
+      corner.transformed_normal = normal;
+      corner.tex_coords = uv;
+    }
+
+    // The centroid (15.5, 10.5) is the center of pixel (15, 10)
+    let fragments = triangle(&corners[0], &corners[1], &corners[2], 32, 32);
+    let centroid = fragments.iter().find(|fragment| fragment.position == Vec2::new(15.0, 10.0)).unwrap();
+    let expected_normal = (normals[0] + normals[1] + normals[2]).normalize();
+    assert!((centroid.normal - expected_normal).magnitude() < 1e-4, "normal {:?}", centroid.normal);
+    assert!((centroid.tex_coords - Vec2::new(0.5, 1.0 / 3.0)).magnitude() < 1e-4, "uv {:?}", centroid.tex_coords);
+  }
+
+  #[test]
+  fn tangent_parallel_to_the_normal_still_gives_a_unit_tangent() {
+    // Vertex::new leaves the tangent at (1, 0, 0), the same as this normal
+    let mut corners = [(2.0, 2.0), (30.0, 2.0), (16.0, 30.0)].map(|(x, y)| screen_vertex(x, y, 0.5));
+    for corner in &mut corners {
+      corner.transformed_normal = Vec3::new(1.0, 0.0, 0.0);
+    }
+    // Opposing tangents that cancel out when interpolated
+    corners[0].transformed_tangent = Vec3::new(0.0, 1.0, 0.0);
+    corners[1].transformed_tangent = Vec3::new(0.0, -1.0, 0.0);
+
+    let fragments = triangle(&corners[0], &corners[1], &corners[2], 32, 32);
+    assert!(!fragments.is_empty());
+    for fragment in &fragments {
+      assert!((fragment.tangent.magnitude() - 1.0).abs() < 1e-4, "tangent {:?}", fragment.tangent);
+      assert!(fragment.tangent.dot(&fragment.normal).abs() < 1e-4);
+    }
+  }
+}
@@ -1,22 +1,185 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec3, Mat3, Mat4, look_at, perspective, mat4_to_mat3, translation};
+use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::Framebuffer;
-use crate::Vertex;
-use crate::Obj;
-use crate::Camera;
-use crate::triangle;
-use crate::{vertex_shader};
-use crate::{FastNoiseLite, NoiseType, FractalType};
+use crate::color::Color;
+use crate::light::Light;
+use crate::texture::Texture;
+use crate::shaders::SHADER_COUNT;
+use crate::crater::{Crater, generate_craters, DEFAULT_CRATER_SEED, DEFAULT_CRATER_COUNT};
+
+// At most this many occluders are kept for the eclipse test
+pub const MAX_OCCLUDERS: usize = 4;
+
+// Clip planes of every perspective matrix. With these matrices clip-space w is
+// the distance in front of the camera, so w < NEAR_PLANE is in front of the near plane.
+pub const NEAR_PLANE: f32 = 0.1;
+pub const FAR_PLANE: f32 = 1000.0;
+
+// World-space plane for cross-sections: points with
+// dot(normal, p) < offset are on the negative side and get discarded
+#[derive(Clone, Copy, Debug)]
+pub struct ClipPlane {
+    pub normal: Vec3,
+    pub offset: f32,
+}
+
+impl ClipPlane {
+    pub fn keeps(&self, world_position: &Vec3) -> bool {
+        self.normal.dot(world_position) >= self.offset
+    }
+}
+
+// Debug view of the triangles themselves: `Hidden` draws the edges a filled
+// render would show (a depth-only pass hides the ones behind surfaces),
+// `All` draws every edge, back faces included, on top of everything
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WireframeMode {
+    Off,
+    Hidden,
+    All,
+}
+
+// World-space bounding sphere of a body that can shadow the one being drawn
+#[derive(Clone, Copy, Debug)]
+pub struct Occluder {
+    pub center: Vec3,
+    pub radius: f32,
+}
 
 pub struct Uniforms {
     pub model_matrix: Mat4,
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
     pub viewport_matrix: Mat4,
-    pub time: u32,
-    pub noise: FastNoiseLite
+    // Simulation clock in seconds, scaled by `time_scale`
+    pub time: f32,
+    pub time_scale: f32,
+    // Separate clock for the bodies' self-rotation, so spin can be sped up,
+    // slowed down or paused without touching the rest of the animation
+    pub spin_time: f32,
+    pub spin_rate: f32,
+    pub spin_paused: bool,
+    // Clock for the orbital motion alone, scaled by `orbit_rate`
+    pub orbit_time: f32,
+    pub orbit_rate: f32,
+    // Drives the vertex displacement, which does not know the planet
+    pub noise: FastNoiseLite,
+    // One field per select_shader index, see noise_for_planet and noise_for
+    pub planet_noise: [FastNoiseLite; SHADER_COUNT],
+    // Fractal noise for Earth's cloud layers only, see create_cloud_layer_noise
+    pub cloud_noise: FastNoiseLite,
+    pub noise_samples: usize,
+    pub light: Light,
+    pub solo_triangle: Option<usize>,
+    // Debug: tint triangles classified as back-facing instead of shading them normally
+    pub tint_backfaces: bool,
+    // Skip back-facing triangles before rasterizing. Not applied while
+    // back faces are wanted: tinted for debugging, or exposed by clip_plane.
+    pub cull_backfaces: bool,
+    // Multiply shader output by the interpolated vertex color
+    pub vertex_colors: bool,
+    // Write shaded colors into the framebuffer's float buffer without the
+    // 8-bit clamp, scaled up by their emission so self-lit surfaces go above
+    // 1.0 and tone mapping decides how they saturate
+    pub hdr: bool,
+    // Draw triangle edges instead of shaded surfaces, see `render`
+    pub wireframe: WireframeMode,
+    // Added to every fragment depth of the current draw; a small negative bias
+    // makes rings or overlays win against coincident surfaces
+    pub depth_bias: f32,
+    // Earth's polar caps start at this |sin(latitude)| (0 = everything, 1 = nothing)
+    pub polar_extent: f32,
+    // Cross-section plane applied per fragment in `render`, None draws everything
+    pub clip_plane: Option<ClipPlane>,
+    // Other bodies that may block the light, see `set_occluders`
+    pub occluders: Vec<Occluder>,
+    // Round craters shared by the rocky shaders, generated once so they stay put
+    pub craters: Vec<Crater>,
+    // Live overrides for shader tuning values, keyed by name; shaders fall
+    // back to their built-in default for anything missing
+    pub params: HashMap<String, f32>,
+    // How far vertex_shader pushes vertices along their normal with noise;
+    // render_rings sets it to 0 so the ring stays flat
+    pub displacement: f32,
+    // Albedo maps keyed by planet ("earth", "mars"), blended with the
+    // procedural surface by the shaders that know them
+    pub textures: HashMap<String, Texture>,
+}
+
+impl Uniforms {
+    pub fn new(projection_matrix: Mat4, viewport_matrix: Mat4) -> Self {
+        Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: Mat4::identity(),
+            projection_matrix,
+            viewport_matrix,
+            time: 0.0,
+            time_scale: 1.0,
+            spin_time: 0.0,
+            spin_rate: 1.0,
+            spin_paused: false,
+            orbit_time: 0.0,
+            orbit_rate: 1.0,
+            noise: create_noise(),
+            planet_noise: std::array::from_fn(noise_for_planet),
+            cloud_noise: create_cloud_layer_noise(),
+            noise_samples: 1,
+            light: Light::new(
+                Vec3::new(20.0, 10.0, 30.0),
+                Color::new(255, 255, 255),
+                1.0,
+            ),
+            solo_triangle: None,
+            tint_backfaces: false,
+            cull_backfaces: true,
+            vertex_colors: false,
+            hdr: false,
+            wireframe: WireframeMode::Off,
+            depth_bias: 0.0,
+            polar_extent: 0.8,
+            clip_plane: None,
+            occluders: Vec::new(),
+            craters: generate_craters(DEFAULT_CRATER_SEED, DEFAULT_CRATER_COUNT),
+            params: HashMap::new(),
+            displacement: 0.5,
+            textures: HashMap::new(),
+        }
+    }
+
+    // Keeps the MAX_OCCLUDERS candidates closest to `position` (the body
+    // being drawn), so the per-fragment shadow test stays cheap
+    pub fn set_occluders(&mut self, position: Vec3, candidates: &[Occluder]) {
+        self.occluders.clear();
+        self.occluders.extend_from_slice(candidates);
+        self.occluders.sort_by(|a, b| {
+            (a.center - position).magnitude().total_cmp(&(b.center - position).magnitude())
+        });
+        self.occluders.truncate(MAX_OCCLUDERS);
+    }
+
+    // Replaces the crater layout; the same seed and count give the same craters
+    pub fn set_craters(&mut self, seed: u64, count: usize) {
+        self.craters = generate_craters(seed, count);
+    }
+
+    // Noise of a select_shader index; unknown indices share `noise`
+    pub fn noise_for(&self, index: usize) -> &FastNoiseLite {
+        self.planet_noise.get(index).unwrap_or(&self.noise)
+    }
+
+    // Moves every clock forward by `elapsed` real seconds, see FrameClock
+    pub fn advance_time(&mut self, elapsed: f32) {
+        let step = elapsed * self.time_scale;
+        self.time += step;
+        self.orbit_time += step * self.orbit_rate;
+        if !self.spin_paused {
+            self.spin_time += step * self.spin_rate;
+        }
+        self.light.advance(step);
+    }
 }
 
 pub fn create_noise() -> FastNoiseLite {
@@ -26,12 +189,72 @@ pub fn create_noise() -> FastNoiseLite {
     // create_lava_noise()
 }
 
+// Noise for the surface of the select_shader `index`, each with its own seed
+// so no two planets share features. Frequencies stay near the default 0.01
+// the shaders' zooms were tuned for. Mercury and Mars keep a single octave:
+// rim_detail measures slopes against it. Unknown indices get create_noise.
+pub fn noise_for_planet(index: usize) -> FastNoiseLite {
+    let (seed, noise_type, fractal, octaves, frequency) = match index {
+        0 => (1337, NoiseType::OpenSimplex2, FractalType::FBm, 3, 0.01),          // Sun: granulation
+        1 => (4211, NoiseType::OpenSimplex2, FractalType::None, 1, 0.01),         // Mercury: crust
+        2 => (2027, NoiseType::OpenSimplex2, FractalType::FBm, 4, 0.008),         // Earth: continents
+        3 => (7717, NoiseType::OpenSimplex2, FractalType::FBm, 3, 0.01),          // Venus: cloud deck
+        4 => (3301, NoiseType::OpenSimplex2S, FractalType::None, 1, 0.01),        // Mars: crust
+        5 => (5923, NoiseType::Perlin, FractalType::FBm, 3, 0.01),                // Jupiter: turbulence
+        6 => (6151, NoiseType::Perlin, FractalType::None, 1, 0.01),               // Saturn
+        7 => (8093, NoiseType::Perlin, FractalType::None, 1, 0.006),              // Uranus: faint bands
+        8 => (9341, NoiseType::Value, FractalType::None, 1, 0.01),                // Rings: ringlets
+        9 => (1049, NoiseType::Perlin, FractalType::FBm, 2, 0.008),               // Neptune: storms
+        10 => (2969, NoiseType::OpenSimplex2, FractalType::FBm, 2, 0.01),         // Moon: maria
+        _ => return create_noise(),
+    };
+
+    let mut noise = FastNoiseLite::with_seed(seed);
+    noise.set_noise_type(Some(noise_type));
+    noise.set_fractal_type(Some(fractal));
+    noise.set_fractal_octaves(Some(octaves));
+    noise.set_frequency(Some(frequency));
+    noise
+}
+
+// Types offered when cycling the noise at runtime, in order
+const NOISE_TYPE_CYCLE: [NoiseType; 4] = [
+    NoiseType::Perlin,
+    NoiseType::OpenSimplex2,
+    NoiseType::Cellular,
+    NoiseType::Value,
+];
+
+// Switches `noise` to the next type in NOISE_TYPE_CYCLE, keeping its seed,
+// frequency and fractal settings, and returns the new type
+pub fn cycle_noise_type(noise: &mut FastNoiseLite) -> NoiseType {
+    let next = NOISE_TYPE_CYCLE.iter()
+        .position(|&noise_type| noise_type == noise.noise_type)
+        .map_or(0, |index| (index + 1) % NOISE_TYPE_CYCLE.len());
+
+    noise.set_noise_type(Some(NOISE_TYPE_CYCLE[next]));
+    NOISE_TYPE_CYCLE[next]
+}
+
 pub fn create_cloud_noise() -> FastNoiseLite {
     let mut noise = FastNoiseLite::with_seed(1337);
     noise.set_noise_type(Some(NoiseType::OpenSimplex2));
     noise
 }
 
+// Several octaves break up the repetition a single octave shows at low zoom.
+// Same frequency as create_noise so the cloud scale stays put, but another
+// seed so the clouds do not follow the continents.
+pub fn create_cloud_layer_noise() -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(7919);
+    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    noise.set_fractal_type(Some(FractalType::FBm));
+    noise.set_fractal_octaves(Some(5));
+    noise.set_fractal_lacunarity(Some(2.0));
+    noise.set_fractal_gain(Some(0.5));
+    noise
+}
+
 pub fn create_cell_noise() -> FastNoiseLite {
     let mut noise = FastNoiseLite::with_seed(1337);
     noise.set_noise_type(Some(NoiseType::Cellular));
@@ -105,25 +328,208 @@ pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat
     transform_matrix * rotation_matrix
 }
 
+// Inverse-transpose of the model's upper 3x3, so normals stay perpendicular
+// to the surface under non-uniform scaling. A singular model matrix (zero
+// scale on some axis) has no inverse; fall back to the plain 3x3, which is
+// still correct for rotations and uniform scale, and report it once.
+pub fn create_normal_matrix(model_matrix: &Mat4) -> Mat3 {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+
+    let model_mat3 = mat4_to_mat3(model_matrix);
+    match model_mat3.try_inverse() {
+        Some(inverse) => inverse.transpose(),
+        None => {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!("warning: model matrix is not invertible, normals use the model matrix directly");
+            }
+            model_mat3
+        }
+    }
+}
 
+// When eye == center, or up is parallel to the view direction, look_at
+// would normalize a zero vector and fill the matrix with NaNs. Fall back to
+// an unrotated view that only moves the world to the eye.
 pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    let forward = center - eye;
+    if forward.magnitude() <= f32::EPSILON || forward.cross(&up).magnitude() <= f32::EPSILON {
+        return translation(&-eye);
+    }
+
     look_at(&eye, &center, &up)
 }
 
-pub fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
-    let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
+// Which extent of the image the field of view angle spans
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FovAxis {
+    // Height is fixed; wider windows show more to the sides
+    Vertical,
+    // Width is fixed; taller windows show more above and below
+    Horizontal,
+}
+
+// Projection for a render target of the given size in pixels. Pass the
+// framebuffer dimensions (the image actually rasterized), not the window's,
+// so the aspect ratio matches the viewport matrix. Uses a 45 degree
+// vertical field of view.
+pub fn create_perspective_matrix(framebuffer_width: f32, framebuffer_height: f32) -> Mat4 {
+    create_perspective_matrix_with_fov(framebuffer_width, framebuffer_height, 45.0, FovAxis::Vertical)
+}
 
-    perspective(fov, aspect_ratio, near, far)
+// Same as `create_perspective_matrix` with an explicit field of view in
+// degrees measured along `axis`; the other angle follows from the aspect ratio
+pub fn create_perspective_matrix_with_fov(
+    framebuffer_width: f32,
+    framebuffer_height: f32,
+    fov_degrees: f32,
+    axis: FovAxis,
+) -> Mat4 {
+    let aspect_ratio = framebuffer_width / framebuffer_height;
+    let fov = fov_degrees.clamp(1.0, 179.0) * PI / 180.0;
+    let vertical_fov = match axis {
+        FovAxis::Vertical => fov,
+        FovAxis::Horizontal => 2.0 * ((fov / 2.0).tan() / aspect_ratio).atan(),
+    };
+    perspective(aspect_ratio, vertical_fov, NEAR_PLANE, FAR_PLANE)
 }
 
-pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+// Maps NDC to pixel coordinates of a framebuffer of the given size. Screen
+// coordinates are continuous with the origin at the top-left corner of the
+// image and Y pointing down: NDC (-1, -1) lands on the bottom-left corner
+// (0, height) and (1, 1) on the top-right corner (width, 0). Pixel (x, y)
+// covers [x, x + 1) x [y, y + 1) and is sampled at its center (x + 0.5,
+// y + 0.5), so no half-pixel offset belongs here. Flipping Y reverses the
+// winding: a counter-clockwise triangle in NDC has a positive
+// `edge_function` area on screen, see `is_back_facing`. NDC z (-1 at the
+// near plane, 1 at the far plane) becomes depth in 0..1, so 0.0 is nearest
+// and smaller depths win the z-buffer test.
+pub fn create_viewport_matrix(framebuffer_width: f32, framebuffer_height: f32) -> Mat4 {
     Mat4::new(
-        width / 2.0, 0.0, 0.0, width / 2.0,
-        0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
+        framebuffer_width / 2.0, 0.0, 0.0, framebuffer_width / 2.0,
+        0.0, -framebuffer_height / 2.0, 0.0, framebuffer_height / 2.0,
+        0.0, 0.0, 0.5, 0.5,
         0.0, 0.0, 0.0, 1.0
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec4;
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).magnitude() < 1e-5, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn to_view(view: &Mat4, point: Vec3) -> Vec3 {
+        (view * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    // A view-space point through projection, perspective divide and viewport
+    fn to_screen(view_point: Vec3) -> Vec3 {
+        let projection = create_perspective_matrix(800.0, 600.0);
+        let viewport = create_viewport_matrix(800.0, 600.0);
+        let clip = projection * Vec4::new(view_point.x, view_point.y, view_point.z, 1.0);
+        let ndc = clip / clip.w;
+        (viewport * ndc).xyz()
+    }
+
+    #[test]
+    fn view_matrix_puts_the_center_on_negative_z() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let view = create_view_matrix(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), up);
+        assert_near(to_view(&view, Vec3::new(0.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -10.0));
+
+        // Off-axis eye: the center is still straight ahead at its distance
+        let eye = Vec3::new(3.0, 4.0, 12.0);
+        let center = Vec3::new(1.0, -2.0, 0.5);
+        let view = create_view_matrix(eye, center, up);
+        assert_near(to_view(&view, center), Vec3::new(0.0, 0.0, -(center - eye).magnitude()));
+    }
+
+    #[test]
+    fn view_matrix_matches_hand_computed_values() {
+        // Eye on +X looking at the origin: right is -Z, up stays +Y
+        let view = create_view_matrix(Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_near(to_view(&view, Vec3::new(0.0, 0.0, -1.0)), Vec3::new(1.0, 0.0, -10.0));
+        assert_near(to_view(&view, Vec3::new(0.0, 2.0, 0.0)), Vec3::new(0.0, 2.0, -10.0));
+        assert_near(to_view(&view, Vec3::new(4.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -6.0));
+    }
+
+    #[test]
+    fn view_matrix_keeps_right_handed_axes() {
+        let view = create_view_matrix(Vec3::new(-4.0, 6.0, 9.0), Vec3::new(2.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let rotation = mat4_to_mat3(&view);
+        assert!((rotation.determinant() - 1.0).abs() < 1e-5);
+
+        let x = rotation * Vec3::new(1.0, 0.0, 0.0);
+        let y = rotation * Vec3::new(0.0, 1.0, 0.0);
+        let z = rotation * Vec3::new(0.0, 0.0, 1.0);
+        assert_near(x.cross(&y), z);
+    }
+
+    #[test]
+    fn degenerate_view_matrix_has_no_nans() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        for view in [
+            create_view_matrix(eye, eye, up),
+            create_view_matrix(eye, eye + up * 5.0, up),
+        ] {
+            assert!(view.iter().all(|value| value.is_finite()));
+            assert_eq!(mat4_to_mat3(&view), Mat3::identity());
+            assert_near(to_view(&view, eye), Vec3::new(0.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn nearer_points_get_smaller_depths() {
+        let near = to_screen(Vec3::new(0.0, 0.0, -2.0));
+        let far = to_screen(Vec3::new(0.0, 0.0, -50.0));
+        assert!((0.0..=1.0).contains(&near.z) && (0.0..=1.0).contains(&far.z));
+        assert!(near.z < far.z, "near depth {} should be below far depth {}", near.z, far.z);
+
+        assert!(to_screen(Vec3::new(0.0, 0.0, -NEAR_PLANE)).z.abs() < 1e-4);
+        assert!((to_screen(Vec3::new(0.0, 0.0, -FAR_PLANE)).z - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fov_axis_fixes_the_chosen_extent() {
+        // 21:9: the angle given is exact along its own axis
+        let half_tan = (30.0 * PI / 180.0).tan();
+        let vertical = create_perspective_matrix_with_fov(2100.0, 900.0, 60.0, FovAxis::Vertical);
+        assert!((vertical[(1, 1)] - 1.0 / half_tan).abs() < 1e-4);
+        let horizontal = create_perspective_matrix_with_fov(2100.0, 900.0, 60.0, FovAxis::Horizontal);
+        assert!((horizontal[(0, 0)] - 1.0 / half_tan).abs() < 1e-4);
+
+        // 90 degrees across an ultrawide window leaves about the default
+        // 45 degrees vertically, so the planet keeps its usual size
+        let ultrawide = create_perspective_matrix_with_fov(2100.0, 900.0, 90.0, FovAxis::Horizontal);
+        let vertical_fov = 2.0 * (1.0 / ultrawide[(1, 1)]).atan() * 180.0 / PI;
+        assert!((40.0..50.0).contains(&vertical_fov), "vertical fov {}", vertical_fov);
+    }
+
+    #[test]
+    fn viewport_maps_ndc_corners_and_depth() {
+        let viewport = create_viewport_matrix(800.0, 600.0);
+        let map = |x: f32, y: f32, z: f32| (viewport * Vec4::new(x, y, z, 1.0)).xyz();
+
+        assert_near(map(-1.0, -1.0, -1.0), Vec3::new(0.0, 600.0, 0.0));
+        assert_near(map(1.0, 1.0, 1.0), Vec3::new(800.0, 0.0, 1.0));
+        assert_near(map(0.0, 0.0, 0.0), Vec3::new(400.0, 300.0, 0.5));
+    }
+
+    #[test]
+    fn planets_get_different_noise_at_the_same_point() {
+        let (x, y, z) = (13.7, -4.2, 8.9);
+        let values: Vec<f32> = (0..SHADER_COUNT).map(|index| noise_for_planet(index).get_noise_3d(x, y, z)).collect();
+        for (index, value) in values.iter().enumerate() {
+            for (other, other_value) in values.iter().enumerate().skip(index + 1) {
+                assert!(value != other_value, "planets {} and {} share noise {}", index, other, value);
+            }
+        }
+
+        let uniforms = Uniforms::new(Mat4::identity(), Mat4::identity());
+        assert!(uniforms.noise_for(2).get_noise_3d(x, y, z) != uniforms.noise_for(4).get_noise_3d(x, y, z));
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4, lerp};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -6,9 +6,16 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  pub tangent: Vec3,
   pub color: Color,
   pub transformed_position: Vec3,
+  // Clip-space position before the perspective divide; w is the distance
+  // in front of the camera
+  pub clip_position: Vec4,
+  // Position after the model matrix, used for lighting in world space
+  pub world_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec3,
 }
 
 impl Vertex {
@@ -17,9 +24,14 @@ impl Vertex {
       position,
       normal,
       tex_coords,
-      color: Color::black(),
+      tangent: Vec3::new(1.0, 0.0, 0.0),
+      // White, so tinting by the vertex color leaves uncolored models alone
+      color: Color::new(255, 255, 255),
       transformed_position: position,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      world_position: position,
       transformed_normal: normal,
+      transformed_tangent: Vec3::new(1.0, 0.0, 0.0),
     }
   }
 
@@ -28,9 +40,13 @@ impl Vertex {
       position,
       normal: Vec3::new(0.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec3::new(0.0, 0.0, 0.0),
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      transformed_tangent: Vec3::new(0.0, 0.0, 0.0),
     }
   }
 
@@ -38,6 +54,24 @@ impl Vertex {
     self.transformed_position = position;
     self.transformed_normal = normal;
   }
+
+  // Every attribute blended linearly, `t` = 0 giving self and 1 giving `other`.
+  // transformed_position is not linear in clip space and is blended as is;
+  // the clipper recomputes it from clip_position.
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: lerp(&self.position, &other.position, t),
+      normal: lerp(&self.normal, &other.normal, t),
+      tex_coords: lerp(&self.tex_coords, &other.tex_coords, t),
+      tangent: lerp(&self.tangent, &other.tangent, t),
+      color: self.color.lerp(&other.color, t),
+      transformed_position: lerp(&self.transformed_position, &other.transformed_position, t),
+      clip_position: lerp(&self.clip_position, &other.clip_position, t),
+      world_position: lerp(&self.world_position, &other.world_position, t),
+      transformed_normal: lerp(&self.transformed_normal, &other.transformed_normal, t),
+      transformed_tangent: lerp(&self.transformed_tangent, &other.transformed_tangent, t),
+    }
+  }
 }
 
 impl Default for Vertex {
@@ -46,9 +80,13 @@ impl Default for Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec3::new(1.0, 0.0, 0.0),
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      transformed_tangent: Vec3::new(1.0, 0.0, 0.0),
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,134 @@
+// Golden-image check: renders a few fixed scenes headlessly and compares
+// them with the reference PNGs in golden/. Fails when any scene drifts too
+// far. Run with BLESS=1 to rewrite the references after an intended visual
+// change.
+//
+//     cargo test --test golden
+//     BLESS=1 cargo test --test golden
+
+use std::env;
+use std::path::Path;
+
+use nalgebra_glm::Vec3;
+
+use model3d::framebuffer::Framebuffer;
+use model3d::obj::Obj;
+use model3d::render::render;
+use model3d::vertex::Vertex;
+use model3d::uniforms::{
+    create_model_matrix, create_perspective_matrix, create_view_matrix, create_viewport_matrix,
+    Uniforms,
+};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+// Relative to the crate root
+const GOLDEN_DIRECTORY: &str = "golden";
+const MESH: &str = "assets/model/sphere.obj";
+const MESH_RADIUS: f32 = 3.5;
+
+// A channel may differ by this much before the pixel counts as changed
+const CHANNEL_TOLERANCE: u8 = 8;
+// Fraction of changed pixels that fails the scene
+const MAX_CHANGED_PIXELS: f32 = 0.005;
+
+struct Scene {
+    name: &'static str,
+    shader: usize,
+    time: f32,
+    rotation: Vec3,
+}
+
+const SCENES: [Scene; 4] = [
+    Scene { name: "sun", shader: 0, time: 0.0, rotation: Vec3::new(0.0, 0.0, 0.0) },
+    Scene { name: "earth", shader: 2, time: 2.0, rotation: Vec3::new(0.0, 0.8, 0.4) },
+    Scene { name: "jupiter", shader: 5, time: 0.0, rotation: Vec3::new(0.0, 0.0, 0.05) },
+    Scene { name: "saturn", shader: 6, time: 0.0, rotation: Vec3::new(0.3, 0.0, 0.2) },
+];
+
+fn render_scene(scene: &Scene, vertex_array: &[Vertex]) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.set_background_color(0x333355);
+    framebuffer.clear();
+
+    let mut uniforms = Uniforms::new(
+        create_perspective_matrix(WIDTH as f32, HEIGHT as f32),
+        create_viewport_matrix(WIDTH as f32, HEIGHT as f32),
+    );
+    uniforms.time = scene.time;
+    uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, scene.rotation);
+    uniforms.view_matrix = create_view_matrix(
+        Vec3::new(0.0, 0.0, 15.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+
+    render(&mut framebuffer, &uniforms, vertex_array, scene.shader);
+    framebuffer
+}
+
+// Fraction of pixels whose channels differ by more than CHANNEL_TOLERANCE
+fn changed_fraction(framebuffer: &Framebuffer, reference: &image::RgbImage) -> f32 {
+    let changed = framebuffer
+        .buffer
+        .iter()
+        .zip(reference.pixels())
+        .filter(|(&pixel, expected)| {
+            let actual = [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8];
+            actual
+                .iter()
+                .zip(expected.0.iter())
+                .any(|(a, e)| a.abs_diff(*e) > CHANNEL_TOLERANCE)
+        })
+        .count();
+
+    changed as f32 / framebuffer.buffer.len() as f32
+}
+
+#[test]
+fn scenes_match_golden_images() {
+    let bless = env::var("BLESS").is_ok_and(|value| value == "1");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let mesh = root.join(MESH);
+    let mut obj = Obj::load(&mesh.to_string_lossy())
+        .unwrap_or_else(|error| panic!("{}: {}", mesh.display(), error));
+    obj.normalize(MESH_RADIUS);
+    let vertex_array = obj.get_vertex_array();
+
+    let mut failures = Vec::new();
+    for scene in &SCENES {
+        let framebuffer = render_scene(scene, &vertex_array);
+        let path = root.join(GOLDEN_DIRECTORY).join(format!("{}.png", scene.name));
+        let path = path.to_string_lossy();
+
+        if bless {
+            match framebuffer.save_png(&path) {
+                Ok(()) => println!("{}: reference written", path),
+                Err(error) => failures.push(format!("{}: {}", path, error)),
+            }
+            continue;
+        }
+
+        let reference = match image::open(path.as_ref()) {
+            Ok(reference) => reference.to_rgb8(),
+            Err(error) => {
+                failures.push(format!("{}: {} (run with BLESS=1 to create it)", path, error));
+                continue;
+            }
+        };
+        if reference.dimensions() != (WIDTH as u32, HEIGHT as u32) {
+            failures.push(format!("{}: reference is {:?}, expected {}x{}", path, reference.dimensions(), WIDTH, HEIGHT));
+            continue;
+        }
+
+        let changed = changed_fraction(&framebuffer, &reference);
+        if changed > MAX_CHANGED_PIXELS {
+            failures.push(format!("{}: {:.2}% of pixels changed", path, changed * 100.0));
+        } else {
+            println!("{}: ok ({:.2}% changed)", path, changed * 100.0);
+        }
+    }
+
+    assert!(failures.is_empty(), "golden images differ:\n{}", failures.join("\n"));
+}